@@ -1,14 +1,34 @@
-use anyhow::{Ok, Result};
-use chrono::Local;
+use anyhow::{Context, Ok, Result};
+use chrono::{Local, NaiveDate};
 use clap::{Parser, Subcommand};
-use cli_core::{config::Config, note::Note, template::TemplArgs, vault::VaultStats};
+use cli_core::{
+    config::{
+        ATTACHMENT_FOLDER_KEY, Config, DAILY_FORMAT_KEY, DAILY_TEMPLATE_KEY, Source, bootstrap,
+        config_file_path,
+    },
+    config_file::{
+        ConfigFile, DAILY_FOLDER_KEY, DEFAULT_PROFILE_KEY, FSYNC_KEY, INBOX_FOLDER_KEY,
+        NOTE_NAME_KEY, OPEN_WITH_KEY, TEMPLATE_KEY, TEMPLATES_FOLDER_KEY, VAULT_KEY,
+    },
+    dates,
+    doctor::LinkReport,
+    editor::{self, OpenWith},
+    frontmatter::Property,
+    links::ResolvedLink,
+    naming::NameVars,
+    note::{Insert, ItemFormat, Note, note_file},
+    periodic::{Period, PeriodicNote},
+    rename::Rename,
+    template::Template,
+    uri::{self, Uri},
+    vault::VaultStats,
+};
 use std::{
-    fs::{self, File},
-    io::{self, Read},
-    path::PathBuf,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
 };
 use termimad::MadSkin;
-use tokio::main;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -23,65 +43,456 @@ struct Args {
     /// Path to template
     #[arg(short, long)]
     template: Option<PathBuf>,
+
+    /// Path to a config file layered over the user config
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Named vault profile from the config file
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Open notes in $VISUAL or $EDITOR instead of Obsidian
+    #[arg(long, global = true)]
+    editor: bool,
 }
 
 #[derive(Subcommand, Debug)]
 #[clap(rename_all = "snake_case")]
 enum Command {
     /// Create a new note from an idea
-    New { idea: Option<String> },
+    New {
+        /// The note's text, `-` or piped stdin to read it from there
+        idea: Option<String>,
+        /// Read the text from this file instead
+        #[arg(long, conflicts_with = "idea")]
+        file: Option<PathBuf>,
+        /// Template from the templates folder, by name
+        #[arg(short, long)]
+        template: Option<String>,
+        /// Template variable, skips its prompt. Can be repeated.
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+        /// Vault-relative folder for the note, instead of the inbox folder
+        #[arg(long)]
+        folder: Option<PathBuf>,
+        /// `{{title}}` of the `note_name` pattern, or the whole file name
+        /// if the pattern has none
+        #[arg(long)]
+        title: Option<String>,
+        /// Edit the rendered note in $VISUAL or $EDITOR before saving it
+        #[arg(long)]
+        edit: bool,
+    },
 
-    /// Edit the config file (todo!)
+    /// List and preview the templates in the templates folder
+    Templates {
+        #[command(subcommand)]
+        action: TemplatesAction,
+    },
+
+    /// Read or edit the config file
     Config {
-        #[arg(short, long)]
-        vault: Option<PathBuf>,
-        #[arg(short, long)]
-        template: Option<PathBuf>,
+        #[command(subcommand)]
+        action: ConfigAction,
     },
 
-    /// Append to an existing note. If not exists, exec "new"
+    /// Append to a note, creating it from the template if it doesn't exist
     Append {
-        #[arg(short)]
-        note: PathBuf,
-        idea: String,
+        #[arg(short, required_unless_present = "daily")]
+        note: Option<PathBuf>,
+        /// The text to append, `-` or piped stdin to read it from there
+        idea: Option<String>,
+        /// Read the text from this file instead
+        #[arg(long, conflicts_with = "idea")]
+        file: Option<PathBuf>,
+        /// Append to the daily note instead, of today or `--daily=yesterday`
+        #[arg(
+            long,
+            conflicts_with = "note",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "today",
+            value_parser = parse_date
+        )]
+        daily: Option<NaiveDate>,
+        /// Fail instead of creating a missing note
+        #[arg(long = "no-create")]
+        no_create: bool,
+        /// Append under this heading, added if missing (`## Log` sets its level)
+        #[arg(long)]
+        heading: Option<String>,
+        /// Insert at the start of the section or note instead of the end
+        #[arg(long)]
+        prepend: bool,
+        /// Insert after the block with this id, e.g. `^abc123`
+        #[arg(long = "after-block", conflicts_with_all = ["heading", "prepend"])]
+        after_block: Option<String>,
+        /// Write the idea as a task, `- [ ] idea`
+        #[arg(long = "as-task", conflicts_with = "as_bullet")]
+        as_task: bool,
+        /// Write the idea as a list item, `- idea`
+        #[arg(long = "as-bullet")]
+        as_bullet: bool,
     },
 
-    /// Open the daily(?) note -> could also be just "Daily"
+    /// Open the daily note of a date, creating it if missing, or any note
     ///
-    /// Example: obsidian://open?vault=TestVault&file=Test%20Note.
-    /// Has to also format the date according to Obsidian's "daily".
-    /// Format as default: "2025-08-15"; "YYYY-MM-DD"
-    Open {},
+    /// Daily notes are named with Obsidian's daily note format, `YYYY-MM-DD`
+    /// by default, and open in the app or the editor like `open_with` says.
+    Open {
+        /// today, yesterday, +3d, last friday, 2026-10-01...
+        #[arg(allow_hyphen_values = true, value_parser = parse_date)]
+        date: Option<NaiveDate>,
+        /// Open this note instead, with an optional `#heading` or `#^block`
+        #[arg(short, conflicts_with = "date")]
+        note: Option<String>,
+        /// Print the note's path instead of opening it
+        #[arg(long = "print-path")]
+        print_path: bool,
+    },
+
+    /// Open a periodic note, creating it from its template if missing
+    Periodic {
+        /// daily, weekly, monthly, quarterly or yearly
+        period: Period,
+        /// Periods before (negative) or after the current one
+        #[arg(long, allow_hyphen_values = true, default_value_t = 0)]
+        offset: i32,
+        /// A date in the period instead of today, e.g. 2026-10-01 or -1w
+        #[arg(long, allow_hyphen_values = true, value_parser = parse_date)]
+        date: Option<NaiveDate>,
+        /// Print the note's path instead of opening it
+        #[arg(long = "print-path")]
+        print_path: bool,
+    },
+
+    /// Print an `obsidian://` URI for a note, a search or a new note
+    Uri {
+        /// Vault-relative note, with an optional `#heading` or `#^block`
+        #[arg(required_unless_present = "search")]
+        note: Option<String>,
+        /// Open the search pane with this query instead
+        #[arg(long, conflicts_with_all = ["note", "content", "advanced"])]
+        search: Option<String>,
+        /// Create the note with this content (`new` action)
+        #[arg(long, conflicts_with = "advanced")]
+        content: Option<String>,
+        /// Use the Advanced URI plugin's `advanced-uri` action
+        #[arg(long)]
+        advanced: bool,
+        /// Extra parameter like `mode=append`. Can be repeated.
+        #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_var)]
+        params: Vec<(String, String)>,
+    },
 
     /// Pretty print a note with formatting
     Show {
+        /// Path, title or alias of the note
         #[arg(short)]
-        note: PathBuf,
+        note: String,
+    },
+
+    /// Print the links of a note and where they lead
+    Links {
+        /// Path, title or alias of the note
+        note: String,
+    },
+
+    /// Print every line of other notes that links to a note
+    Backlinks {
+        /// Path, title or alias of the note
+        note: String,
+    },
+
+    /// Move or rename a note, attachment or folder, updating every link to it
+    Mv {
+        /// Path of the note, attachment or folder, or a note's title or alias
+        old: String,
+        /// New path inside the vault, or an existing folder to move into
+        new: PathBuf,
+        /// Print the changes as a diff without making them
+        #[arg(long = "dry-run")]
+        dry_run: bool,
     },
 
     /// Print statistics of the vault
     Stats {},
+
+    /// Read or edit the frontmatter properties of a note
+    Props {
+        #[command(subcommand)]
+        action: PropsAction,
+    },
+
+    /// Manage the saved index of the vault's notes
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+
+    /// Check the vault for problems
+    Doctor {
+        #[command(subcommand)]
+        action: DoctorAction,
+    },
 }
 
-// Make a main struct to hold the exec functions -> core
-#[main]
-async fn main() -> anyhow::Result<()> {
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "snake_case")]
+enum PropsAction {
+    /// Print a property, lists one item per line
+    Get { note: String, key: String },
+
+    /// Set a property, e.g. `true`, `3`, `[a, b]` or text. Other keys are left as they are.
+    Set {
+        note: String,
+        key: String,
+        value: Option<String>,
+    },
+
+    /// Remove a property
+    Remove { note: String, key: String },
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "snake_case")]
+enum DoctorAction {
+    /// Report broken links and embeds, orphans, dead ends and unused
+    /// attachments. Exits with 1 when links or embeds are broken.
+    Links {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Also exit with 1 for orphans, dead ends and unused attachments
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "snake_case")]
+enum IndexAction {
+    /// Parse every note again and save the index
+    Rebuild {},
+
+    /// Print where the index is and what changed in the vault since
+    Status {},
+
+    /// Remove the saved index
+    Clear {},
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "snake_case")]
+enum TemplatesAction {
+    /// Print every template with its description
+    List {},
+
+    /// Print a template rendered with sample values
+    Show { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "snake_case")]
+enum ConfigAction {
+    /// Print the value of a key
+    Get { key: String },
+
+    /// Validate and store a value, keeping comments in the file
+    Set { key: String, value: String },
+
+    /// Print every key in the config file
+    List {},
+
+    /// Print the resolved config and where each value came from
+    Resolve {},
+
+    /// Print the path of the config file
+    Path {},
+
+    /// Write a starter config file and template
+    Init {
+        #[arg(short, long)]
+        vault: Option<PathBuf>,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        /// Overwrite an existing config file
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+fn main() -> Result<()> {
     let args = Args::parse();
-    let cfg = Config::build(args.vault, args.template)?;
+    // The config commands must work even when the current config is invalid
+    if let Command::Config { action } = &args.command
+        && !matches!(action, ConfigAction::Resolve {})
+    {
+        let path = args.config.map_or_else(config_file_path, Ok)?;
+        return exec_config(action, path, args.profile.as_deref());
+    }
+    let mut cfg = Config::build(args.vault, args.template, args.config, args.profile)?;
+    if args.editor {
+        cfg.open_with = OpenWith::Editor;
+        cfg.sources.insert(OPEN_WITH_KEY, Source::Flag);
+    }
 
     match args.command {
-        Command::New { idea } => exec_new_note(idea, &cfg),
-        Command::Append { note, idea } => exec_append_note(idea, note, &cfg),
-        Command::Open {} => exec_open_daily(&cfg),
+        Command::New {
+            idea,
+            file,
+            template,
+            vars,
+            folder,
+            title,
+            edit,
+        } => {
+            let templ = cfg.select_template(template.as_deref())?;
+            let idea = read_idea(idea, file)?;
+            exec_new_note(idea, &templ, vars, folder, title, edit, &cfg)
+        }
+        Command::Templates { action } => exec_templates(action, &cfg),
+        Command::Append {
+            note,
+            idea,
+            file,
+            daily,
+            no_create,
+            heading,
+            prepend,
+            after_block,
+            as_task,
+            as_bullet,
+        } => {
+            let format = match (as_task, as_bullet) {
+                (true, _) => ItemFormat::Task,
+                (_, true) => ItemFormat::Bullet,
+                _ => ItemFormat::Plain,
+            };
+            let at = Insert {
+                heading,
+                after_block,
+                prepend,
+                format,
+            };
+            let idea = read_idea(idea, file)?;
+            exec_append_note(idea, note, daily, &at, !no_create, &cfg)
+        }
+        Command::Open {
+            note: Some(note),
+            print_path,
+            ..
+        } => exec_open_note(&note, print_path, &cfg),
+        Command::Open {
+            date, print_path, ..
+        } => exec_periodic(Period::Daily, 0, date, print_path, &cfg),
+        Command::Uri {
+            note,
+            search,
+            content,
+            advanced,
+            params,
+        } => exec_uri(note, search, content, advanced, params, &cfg),
+        Command::Periodic {
+            period,
+            offset,
+            date,
+            print_path,
+        } => exec_periodic(period, offset, date, print_path, &cfg),
         Command::Show { note } => exec_show_note(note, &cfg),
-        Command::Stats {} => exec_vault_stats(&cfg).await,
-        _ => Err(anyhow::Error::msg("Invalid command")), // Automatically does this -> clap?
+        Command::Links { note } => exec_links(&note, &cfg),
+        Command::Backlinks { note } => exec_backlinks(&note, &cfg),
+        Command::Mv { old, new, dry_run } => exec_mv(&old, &new, dry_run, &cfg),
+        Command::Stats {} => exec_vault_stats(&cfg),
+        Command::Props { action } => exec_props(action, &cfg),
+        Command::Index { action } => exec_index(action, &cfg),
+        Command::Doctor {
+            action: DoctorAction::Links { json, strict },
+        } => exec_doctor_links(json, strict, &cfg),
+        Command::Config { .. } => exec_config_resolve(&cfg),
+    }
+}
+
+fn exec_config(action: &ConfigAction, path: PathBuf, profile: Option<&str>) -> Result<()> {
+    match action {
+        ConfigAction::Get { key } => {
+            let file = ConfigFile::load(path)?;
+            println!("{}", file.get(profile, key)?.unwrap_or_default());
+        }
+        ConfigAction::Set { key, value } => {
+            let mut file = ConfigFile::load(path)?;
+            file.set(profile, key, value)?;
+            file.save()?;
+            println!("Set {key} = {value:?}");
+        }
+        ConfigAction::List {} => {
+            let file = ConfigFile::load(path)?;
+            for (key, value) in file.list() {
+                println!("{key} = {value:?}");
+            }
+        }
+        ConfigAction::Path {} => println!("{}", path.display()),
+        ConfigAction::Resolve {} => unreachable!("resolved configs need a built Config"),
+        ConfigAction::Init {
+            vault,
+            template,
+            force,
+        } => {
+            let mut file = bootstrap(path, *force)?;
+            if let Some(vault) = vault {
+                file.set(profile, VAULT_KEY, &vault.to_string_lossy())?;
+            }
+            if let Some(template) = template {
+                file.set(profile, TEMPLATE_KEY, &template.to_string_lossy())?;
+            }
+            if let Some(profile) = profile {
+                file.set(None, DEFAULT_PROFILE_KEY, profile)?;
+            }
+            file.save()?;
+            println!("Created config: {}", file.path.display());
+        }
+    }
+    Ok(())
+}
+
+fn exec_config_resolve(cfg: &Config) -> Result<()> {
+    let values = [
+        (DEFAULT_PROFILE_KEY, cfg.profile.clone().unwrap_or_default()),
+        (VAULT_KEY, cfg.vault.display().to_string()),
+        (TEMPLATE_KEY, cfg.template.path.display().to_string()),
+        (
+            TEMPLATES_FOLDER_KEY,
+            cfg.templates_folder
+                .as_ref()
+                .map(|folder| folder.display().to_string())
+                .unwrap_or_default(),
+        ),
+        (DAILY_FOLDER_KEY, cfg.daily_folder.display().to_string()),
+        (DAILY_FORMAT_KEY, cfg.daily_format.clone()),
+        (
+            DAILY_TEMPLATE_KEY,
+            cfg.daily_template
+                .as_ref()
+                .map(|templ| templ.path.display().to_string())
+                .unwrap_or_default(),
+        ),
+        (INBOX_FOLDER_KEY, cfg.new_note_folder.display().to_string()),
+        (ATTACHMENT_FOLDER_KEY, cfg.attachment_folder.clone()),
+        (NOTE_NAME_KEY, cfg.note_name.clone()),
+        (FSYNC_KEY, cfg.fsync.to_string()),
+        (OPEN_WITH_KEY, cfg.open_with.to_string()),
+    ];
+    for (key, value) in values {
+        if let Some(source) = cfg.sources.get(key) {
+            println!("{key} = {value:?} ({source})");
+        }
     }
+    Ok(())
 }
 
-async fn exec_vault_stats(cfg: &Config) -> Result<()> {
-    let mut stats = VaultStats::default();
-    stats.walk_vault(cfg).await?;
+fn exec_vault_stats(cfg: &Config) -> Result<()> {
+    let stats = VaultStats::new(&cfg.index()?);
 
     println!("Vault Links: {}", stats.total_link_count);
     println!("Vault Words: {}", stats.total_word_count);
@@ -94,73 +505,391 @@ async fn exec_vault_stats(cfg: &Config) -> Result<()> {
     Ok(())
 }
 
-fn exec_new_note(idea: Option<String>, cfg: &Config) -> Result<()> {
-    let mut note_path: PathBuf = cfg.vault.clone();
+fn exec_links(query: &str, cfg: &Config) -> Result<()> {
+    let vault = cfg.index()?;
+    let path = vault.find(query)?.path.clone();
+    let note = Note::load(&cfg.vault, &path)?;
+    for ResolvedLink { link, target } in vault.resolve_links(&path) {
+        let text = &note.content[link.range.clone()];
+        let line = editor::line_of(&note.content, link.range.start);
+        match target.path() {
+            Some(target) => {
+                let subpath = link
+                    .subpath
+                    .map(|sub| format!("#{sub}"))
+                    .unwrap_or_default();
+                println!("{line}: {text} -> {}{subpath}", target.display());
+            }
+            None => println!("{line}: {text} -> unresolved"),
+        }
+    }
+    Ok(())
+}
 
-    if let Some(idea) = idea {
-        let formatted = format!("{}", Local::now().format("%Y_%m_%d_%H_%M_%S"));
-        note_path.push(format!("Note_{formatted}.md"));
+fn exec_backlinks(query: &str, cfg: &Config) -> Result<()> {
+    let vault = cfg.index()?;
+    let path = vault.find(query)?.path.clone();
+    for backlink in vault.backlinks(&path)? {
+        println!(
+            "{}:{}: {}",
+            backlink.from.display(),
+            backlink.line,
+            backlink.context
+        );
+    }
+    Ok(())
+}
 
-        let handle = File::create(note_path.as_path())?;
+fn exec_mv(old: &str, new: &Path, dry_run: bool, cfg: &Config) -> Result<()> {
+    let vault = cfg.index()?;
+    let in_vault = |path: &Path| path.strip_prefix(&cfg.vault).unwrap_or(path).to_path_buf();
+    let from = match cfg.vault.join(old).exists() {
+        true => in_vault(Path::new(old)),
+        false => vault.find(old)?.path.clone(),
+    };
+    let rename = Rename::plan(&vault, &from, &in_vault(new), cfg.obsidian.link_format())?;
 
-        let body = cfg.template.render(&TemplArgs {
-            body: idea,
-            date: formatted,
-        })?;
+    if dry_run {
+        println!(
+            "Would move {} to {}",
+            rename.from.display(),
+            rename.to.display()
+        );
+        print!("{}", rename.diff());
+        return Ok(());
+    }
+    rename.apply(cfg.fsync)?;
+    println!("Moved {} to {}", rename.from.display(), rename.to.display());
+    for edit in &rename.edits {
+        println!("Updated links in {}", rename.moved(&edit.path).display());
+    }
+    Ok(())
+}
 
-        let mut note = Note::new(&handle, &note_path, Some(body));
-        note.write_file_handle()?;
+fn exec_doctor_links(json: bool, strict: bool, cfg: &Config) -> Result<()> {
+    let report = LinkReport::new(&cfg.index()?)?;
 
-        println!("Created note: {note}");
-        Ok(())
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
     } else {
-        // Prompt for the idea -> make better later
-        let mut idea_buffer = String::new();
-        while idea_buffer.trim_ascii().is_empty() {
-            idea_buffer.clear();
+        let broken = [
+            ("Unresolved links", &report.unresolved),
+            ("Missing embeds", &report.missing_embeds),
+        ];
+        for (title, links) in broken.into_iter().filter(|(_, links)| !links.is_empty()) {
+            println!("{title} ({}):", links.len());
+            for link in links {
+                println!("    {}:{}: {}", link.from.display(), link.line, link.link);
+            }
+        }
+        let unlinked = [
+            ("Orphans", &report.orphans),
+            ("Dead ends", &report.dead_ends),
+            ("Unused attachments", &report.unused_attachments),
+        ];
+        for (title, paths) in unlinked.into_iter().filter(|(_, paths)| !paths.is_empty()) {
+            println!("{title} ({}):", paths.len());
+            for path in paths {
+                println!("    {}", path.display());
+            }
+        }
+        if report.is_empty() {
+            println!("No problems found");
+        }
+    }
+
+    match report.has_broken_links() || strict && !report.is_empty() {
+        true => Err(anyhow::Error::msg("The vault has link problems")),
+        false => Ok(()),
+    }
+}
+
+fn exec_index(action: IndexAction, cfg: &Config) -> Result<()> {
+    let cache = cfg.index_cache()?;
+    match action {
+        IndexAction::Rebuild {} => {
+            cache.clear()?;
+            let (vault, _) = cfg.refresh_index(true)?;
+            println!(
+                "Indexed {} notes, {} attachments and {} folders",
+                vault.notes.len(),
+                vault.attachments.len(),
+                vault.folders.len()
+            );
+        }
+        IndexAction::Status {} => {
+            println!("Index: {}", cache.path.display());
+            match cache.read(&cfg.vault)? {
+                Some(saved) => println!("Saved notes: {}", saved.notes.len()),
+                None => println!("Saved notes: none, the next run indexes the vault"),
+            }
+            let (_, refresh) = cfg.refresh_index(false)?;
+            println!(
+                "Since then: {} unchanged, {} touched, {} new or changed, {} removed",
+                refresh.reused, refresh.touched, refresh.parsed, refresh.removed
+            );
+        }
+        IndexAction::Clear {} => match cache.clear()? {
+            true => println!("Removed {}", cache.path.display()),
+            false => println!("No index at {}", cache.path.display()),
+        },
+    }
+    Ok(())
+}
+
+fn parse_var(arg: &str) -> Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((key, val)) if !key.trim().is_empty() => Ok((key.trim().to_string(), val.to_string())),
+        _ => Err(anyhow::Error::msg(format!(
+            "expected KEY=VALUE, got `{arg}`"
+        ))),
+    }
+}
+
+/// The text from the argument, from `--file`, or from stdin when the
+/// argument is `-` or stdin is piped. On a terminal it is asked for.
+fn read_idea(idea: Option<String>, file: Option<PathBuf>) -> Result<String> {
+    let text = match (idea, file) {
+        (_, Some(file)) => fs::read_to_string(&file)
+            .with_context(|| format!("Could not read {}", file.display()))?,
+        (Some(idea), None) if idea != "-" => idea,
+        (None, None) if io::stdin().is_terminal() => {
             println!("Please enter your idea (end with Ctrl-D):");
-            io::stdin().read_line(&mut idea_buffer)?;
+            io::read_to_string(io::stdin())?
         }
-        exec_new_note(Some(idea_buffer), cfg)
+        _ => io::read_to_string(io::stdin())?,
+    };
+    let text = text.trim_end_matches(['\r', '\n']);
+    if text.trim().is_empty() {
+        return Err(anyhow::Error::msg("Nothing to write, the idea is empty"));
     }
+    Ok(text.to_string())
 }
 
-fn exec_append_note(idea: String, note: PathBuf, cfg: &Config) -> Result<()> {
-    let abs_path = cfg.get_full_path(&note)?;
-    let handle = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(abs_path.as_path())?;
+/// A date relative to today, see `dates::parse`
+fn parse_date(arg: &str) -> Result<NaiveDate> {
+    dates::parse(arg, Local::now().date_naive())
+}
 
-    let mut note = Note::new(&handle, &abs_path, None);
-    note.append(&idea)?;
+/// Asks for every template prompt not given with `--var`
+fn prompt_vars(templ: &Template, mut vars: Vec<(String, String)>) -> Result<Vec<(String, String)>> {
+    for prompt in templ.prompts()? {
+        if vars.iter().any(|(key, _)| *key == prompt) {
+            continue;
+        }
+        print!("{prompt}: ");
+        io::stdout().flush()?;
+        let mut val = String::new();
+        io::stdin().read_line(&mut val)?;
+        vars.push((prompt, val.trim_end_matches(['\r', '\n']).to_string()));
+    }
+    Ok(vars)
+}
 
-    println!("Appended to note: {note}");
+fn exec_templates(action: TemplatesAction, cfg: &Config) -> Result<()> {
+    let library = cfg.library()?;
+    match action {
+        TemplatesAction::List {} => {
+            for (name, templ) in library.list()? {
+                match templ.meta().description {
+                    Some(description) => println!("{name} - {description}"),
+                    None => println!("{name}"),
+                }
+            }
+        }
+        TemplatesAction::Show { name } => {
+            let templ = library.get(&name)?;
+            let mut context = cfg.template_context(Local::now().naive_local(), "Sample note");
+            for prompt in templ.prompts()? {
+                let sample = format!("<{prompt}>");
+                context.insert(prompt, sample);
+            }
+            context.insert("body", "Sample body");
+            print!("{}", templ.render(&context)?);
+        }
+    }
     Ok(())
 }
 
-fn exec_open_daily(cfg: &Config) -> Result<()> {
-    let mut note_path: PathBuf = cfg.vault.clone();
-    let formatted = format!("{}", Local::now().format("%Y-%m-%d"));
-    note_path.push(format!("{formatted}.md"));
+fn exec_new_note(
+    idea: String,
+    templ: &Template,
+    vars: Vec<(String, String)>,
+    folder: Option<PathBuf>,
+    title: Option<String>,
+    edit: bool,
+    cfg: &Config,
+) -> Result<()> {
+    let now = Local::now();
+    let name_vars = NameVars {
+        title: title.as_deref(),
+        idea: &idea,
+    };
+    let note_path = cfg.new_note_path(templ, &now, folder.as_deref(), &name_vars)?;
+    let title = note_path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut context = cfg.template_context(now.naive_local(), &title);
+    for (key, val) in prompt_vars(templ, vars)? {
+        context.insert(key, val);
+    }
+    context.insert("body", idea);
+    let mut content = templ.render(&context)?;
+    if edit {
+        let name = note_path.file_name().unwrap_or_default().to_string_lossy();
+        content = editor::edit_text(&cfg.open_with.command()?, &name, &content)?;
+        if content.trim().is_empty() {
+            return Err(anyhow::Error::msg("Aborting, the note is empty"));
+        }
+    }
+    let mut note = Note::new(note_path, content).with_fsync(cfg.fsync);
+    note.create()?;
+
+    println!("Created note: {note}");
+    Ok(())
+}
 
-    let vault_name = cfg
-        .vault
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("");
+fn exec_append_note(
+    idea: String,
+    note: Option<PathBuf>,
+    daily: Option<NaiveDate>,
+    at: &Insert,
+    create: bool,
+    cfg: &Config,
+) -> Result<()> {
+    let (note, created) = match (note, daily) {
+        (Some(note), _) => {
+            // A title or alias of an existing note, otherwise a path to create
+            let path = note_file(&note);
+            let path = match cfg.vault.join(&path).is_file() {
+                true => path,
+                false => {
+                    let query = note.to_string_lossy();
+                    let vault = cfg.index()?;
+                    match vault.matches(&query).is_empty() {
+                        true => path,
+                        false => vault.find(&query)?.path.clone(),
+                    }
+                }
+            };
+            cfg.append_to_note(&path, &idea, at, create)?
+        }
+        (None, date) => {
+            let date = date.unwrap_or_else(|| Local::now().date_naive());
+            PeriodicNote::resolve(cfg, Period::Daily)?.append(cfg, date, &idea, at, create)?
+        }
+    };
 
-    Note::open(note_path, vault_name, formatted)?;
+    if created {
+        println!("Created note: {note}");
+    } else {
+        println!("Appended to note: {note}");
+    }
     Ok(())
 }
 
-fn exec_show_note(note_path: PathBuf, cfg: &Config) -> Result<()> {
-    let abs_path = cfg.get_full_path(&note_path)?;
-    let mut handle = File::open(abs_path.as_path())?;
+fn exec_periodic(
+    period: Period,
+    offset: i32,
+    date: Option<NaiveDate>,
+    print_path: bool,
+    cfg: &Config,
+) -> Result<()> {
+    let periodic = PeriodicNote::resolve(cfg, period)?;
+    let date = periodic.date(date.unwrap_or_else(|| Local::now().date_naive()), offset)?;
+    if print_path {
+        println!("{}", cfg.vault.join(periodic.path(date)).display());
+        return Ok(());
+    }
+    let (note, created) = periodic.ensure(cfg, date)?;
+    if created {
+        println!("Created note: {note}");
+    }
+    note.open(&cfg.vault, None, &cfg.open_with)
+}
+
+fn exec_open_note(target: &str, print_path: bool, cfg: &Config) -> Result<()> {
+    let (file, subpath) = uri::split_subpath(target);
+    let note = Note::load(&cfg.vault, &cfg.find_note(file)?)?;
+    if let Some(subpath) = subpath
+        && note.subpath(subpath).is_none()
+    {
+        return Err(anyhow::Error::msg(format!(
+            "No heading or block `{subpath}` in {note}"
+        )));
+    }
+    if print_path {
+        println!("{note}");
+        return Ok(());
+    }
+    note.open(&cfg.vault, subpath, &cfg.open_with)
+}
+
+fn exec_uri(
+    note: Option<String>,
+    search: Option<String>,
+    content: Option<String>,
+    advanced: bool,
+    params: Vec<(String, String)>,
+    cfg: &Config,
+) -> Result<()> {
+    let mut uri = match (search, note) {
+        (Some(query), _) => Uri::search(&cfg.vault, &query),
+        (None, Some(target)) => {
+            let (file, subpath) = uri::split_subpath(&target);
+            let file = note_file(Path::new(file));
+            match content {
+                Some(content) => Uri::create(&cfg.vault, &file, &content),
+                None if advanced => Uri::advanced(&cfg.vault, &file, subpath),
+                None => Uri::open(&cfg.vault, &file, subpath),
+            }
+        }
+        (None, None) => return Err(anyhow::Error::msg("Expected a note or --search")),
+    };
+    for (key, val) in params {
+        uri = uri.param(&key, &val);
+    }
+    println!("{uri}");
+    Ok(())
+}
+
+fn exec_props(action: PropsAction, cfg: &Config) -> Result<()> {
+    let (PropsAction::Get { note, .. }
+    | PropsAction::Set { note, .. }
+    | PropsAction::Remove { note, .. }) = &action;
+    let mut note = Note::load(&cfg.vault, &cfg.find_note(note)?)?.with_fsync(cfg.fsync);
+
+    match action {
+        PropsAction::Get { key, .. } => {
+            for val in note
+                .properties()
+                .get(&key)
+                .iter()
+                .flat_map(Property::to_list)
+            {
+                println!("{val}");
+            }
+            return Ok(());
+        }
+        PropsAction::Set { key, value, .. } => {
+            let value = Property::parse(value.as_deref().unwrap_or_default());
+            note.set_property(&key, value.clone());
+            println!("Set {key} = {value} in {note}");
+        }
+        PropsAction::Remove { key, .. } => {
+            if !note.remove_property(&key) {
+                println!("No property {key} in {note}");
+                return Ok(());
+            }
+            println!("Removed {key} from {note}");
+        }
+    }
+
+    note.save()
+}
 
-    let mut buf = String::new();
-    handle.read_to_string(&mut buf)?;
-    buf = buf.replace("==", "`");
+fn exec_show_note(query: String, cfg: &Config) -> Result<()> {
+    let note = Note::load(&cfg.vault, &cfg.find_note(&query)?)?;
+    let buf = note.content.replace("==", "`");
 
     let mut skin = MadSkin::default();
     skin.inline_code
@@ -0,0 +1,469 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result};
+
+/// How many `Name 1.md`, `Name 2.md`... to try before giving up
+const MAX_SUFFIX: usize = 1000;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces `path` by writing a hidden temp file next to it and renaming it
+/// over, so readers see either the old or the new contents, never a part.
+/// A symlink is followed and its target replaced, keeping its permissions.
+/// `fsync` flushes the file and its folder to disk before returning.
+pub fn write(path: &Path, contents: &[u8], fsync: bool) -> Result<()> {
+    let path = &follow_symlink(path);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let temp = temp_path(path);
+
+    let result = write_temp(&temp, contents, fsync)
+        .and_then(|_| keep_permissions(path, &temp))
+        .and_then(|_| {
+            fs::rename(&temp, path).with_context(|| format!("Could not replace {}", path.display()))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result?;
+
+    if fsync {
+        sync_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// Writes a new file without ever replacing an existing one. If `path` is
+/// taken, ` 1`, ` 2`... is added to the file name like Obsidian does.
+/// The contents are written to a temp file that is then hard linked to the
+/// free name, which fails when another writer took it first, so the note
+/// never exists empty or half written. File systems without hard links,
+/// like FAT or some network shares, get the file created and then written.
+/// Returns the path that was written.
+pub fn create_new(path: &Path, contents: &[u8], fsync: bool) -> Result<PathBuf> {
+    create(path, contents, fsync, MAX_SUFFIX)?.ok_or_else(|| {
+        anyhow::Error::msg(format!(
+            "Could not find a free file name for {}",
+            path.display()
+        ))
+    })
+}
+
+/// Like `create_new`, but only ever at `path`. Returns false without
+/// touching anything when the file already exists.
+pub fn create_exact(path: &Path, contents: &[u8], fsync: bool) -> Result<bool> {
+    Ok(create(path, contents, fsync, 1)?.is_some())
+}
+
+/// Creates the first free one of `names` suffixed names, `None` if all
+/// are taken
+fn create(path: &Path, contents: &[u8], fsync: bool, names: usize) -> Result<Option<PathBuf>> {
+    let temp = temp_path(path);
+    write_temp(&temp, contents, fsync)?;
+    let result = free_name(path, names, |candidate| fs::hard_link(&temp, candidate));
+    let _ = fs::remove_file(&temp);
+    let created = match result {
+        Err(err) if no_hard_links(&err) => create_unlinked(path, contents, fsync, names)?,
+        result => result?,
+    };
+
+    if fsync && created.is_some() {
+        sync_dir(path.parent().unwrap_or(Path::new("")))?;
+    }
+    Ok(created)
+}
+
+/// `create` for file systems without hard links
+fn create_unlinked(
+    path: &Path,
+    contents: &[u8],
+    fsync: bool,
+    names: usize,
+) -> Result<Option<PathBuf>> {
+    free_name(path, names, |candidate| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(candidate)?;
+        let result = file
+            .write_all(contents)
+            .and_then(|_| if fsync { file.sync_all() } else { Ok(()) });
+        if result.is_err() {
+            let _ = fs::remove_file(candidate);
+        }
+        result
+    })
+}
+
+/// Creates the first of `Name.md`, `Name 1.md`... up to `names` of them
+/// that `create` doesn't find taken
+fn free_name(
+    path: &Path,
+    names: usize,
+    create: impl Fn(&Path) -> io::Result<()>,
+) -> Result<Option<PathBuf>> {
+    for n in 0..names {
+        let candidate = with_suffix(path, n);
+        match create(&candidate) {
+            Result::Ok(_) => return Ok(Some(candidate)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Could not create {}", path.display()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether linking failed because the file system has no hard links
+fn no_hard_links(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>().is_some_and(|err| {
+        matches!(
+            err.kind(),
+            ErrorKind::Unsupported | ErrorKind::PermissionDenied
+        )
+    })
+}
+
+/// Writes and moves several files so that either all of it happens or,
+/// as far as the file system allows, none of it. New contents are staged
+/// in temp files before anything is replaced, and replaced files, moves
+/// and the folders created for them are undone when a later step fails.
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    writes: Vec<(PathBuf, Vec<u8>)>,
+    moves: Vec<(PathBuf, PathBuf)>,
+    fsync: bool,
+}
+
+impl Transaction {
+    pub fn new(fsync: bool) -> Self {
+        Self {
+            fsync,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces an existing file
+    pub fn write(&mut self, path: PathBuf, contents: impl Into<Vec<u8>>) {
+        self.writes.push((path, contents.into()));
+    }
+
+    /// Moves a file or folder, after every write. The destination must not
+    /// exist, its parent folders are created.
+    pub fn rename(&mut self, from: PathBuf, to: PathBuf) {
+        self.moves.push((from, to));
+    }
+
+    pub fn commit(self) -> Result<()> {
+        for (_, to) in &self.moves {
+            if to.exists() {
+                return Err(anyhow::Error::msg(format!(
+                    "{} already exists",
+                    to.display()
+                )));
+            }
+        }
+
+        // Stage every write, keeping what is replaced to roll back to
+        let mut staged: Vec<(PathBuf, PathBuf, Vec<u8>)> = vec![];
+        let mut result = Ok(());
+        for (path, contents) in &self.writes {
+            let path = follow_symlink(path);
+            let temp = temp_path(&path);
+            let old = fs::read(&path).with_context(|| format!("Could not read {}", path.display()));
+            result = old.and_then(|old| {
+                write_temp(&temp, contents, self.fsync)?;
+                let kept = keep_permissions(&path, &temp);
+                staged.push((path, temp, old));
+                kept
+            });
+            if result.is_err() {
+                break;
+            }
+        }
+        if let Err(err) = result {
+            for (_, temp, _) in &staged {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(err);
+        }
+
+        let mut replaced = vec![];
+        let mut moved = vec![];
+        let mut created_dirs = vec![];
+        let result = staged
+            .iter()
+            .try_for_each(|(path, temp, old)| {
+                fs::rename(temp, path)
+                    .with_context(|| format!("Could not replace {}", path.display()))?;
+                replaced.push((path, old));
+                Ok(())
+            })
+            .and_then(|_| {
+                self.moves.iter().try_for_each(|(from, to)| {
+                    if let Some(dir) = to.parent() {
+                        let missing = dir
+                            .ancestors()
+                            .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                            .map(Path::to_path_buf)
+                            .collect::<Vec<_>>();
+                        fs::create_dir_all(dir)?;
+                        // Outermost first, so they are removed innermost first
+                        created_dirs.extend(missing.into_iter().rev());
+                    }
+                    fs::rename(from, to).with_context(|| {
+                        format!("Could not move {} to {}", from.display(), to.display())
+                    })?;
+                    moved.push((from, to));
+                    Ok(())
+                })
+            });
+
+        if let Err(err) = result {
+            for (from, to) in moved.into_iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            for dir in created_dirs.iter().rev() {
+                let _ = fs::remove_dir(dir);
+            }
+            for (path, old) in replaced {
+                let _ = write(path, old, self.fsync);
+            }
+            for (_, temp, _) in &staged {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(err);
+        }
+
+        if self.fsync {
+            // Written files may have moved along with their folder
+            let dirs = staged
+                .iter()
+                .map(|(path, _, _)| path)
+                .chain(self.moves.iter().flat_map(|(from, to)| [from, to]))
+                .filter_map(|path| path.parent())
+                .chain(self.moves.iter().map(|(_, to)| to.as_path()))
+                .filter(|dir| dir.as_os_str().is_empty() || dir.is_dir());
+            for dir in dirs {
+                sync_dir(dir)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A hidden temp file next to `path`, unique to this process
+fn temp_path(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    dir.join(format!(
+        ".{name}.{}-{}.tmp",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// The file a symlink points to, so writing it keeps the link
+fn follow_symlink(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Result::Ok(meta) if meta.file_type().is_symlink() => {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Gives the temp file the permissions of the file it replaces
+fn keep_permissions(path: &Path, temp: &Path) -> Result<()> {
+    if let Result::Ok(meta) = fs::metadata(path) {
+        fs::set_permissions(temp, meta.permissions())
+            .with_context(|| format!("Could not set permissions of {}", temp.display()))?;
+    }
+    Ok(())
+}
+
+fn write_temp(temp: &Path, contents: &[u8], fsync: bool) -> Result<()> {
+    let mut file = File::create(temp)
+        .with_context(|| format!("Could not create temp file {}", temp.display()))?;
+    file.write_all(contents)?;
+    if fsync {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// `Name.md`, `Name 1.md`, `Name 2.md`...
+fn with_suffix(path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem} {n}.{}", ext.to_string_lossy()),
+        None => format!("{stem} {n}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Persists the rename itself. Folders can't be opened for syncing on Windows.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{self, ErrorKind},
+        path::Path,
+    };
+
+    use anyhow::Context;
+
+    use crate::atomic::{
+        Transaction, create_exact, create_new, create_unlinked, no_hard_links, write,
+    };
+
+    #[test]
+    fn create_never_replaces() {
+        let dir = env::temp_dir().join("obsidian-rust-cli-atomic");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Note.md");
+
+        assert_eq!(create_new(&path, b"one", false).unwrap(), path);
+        assert_eq!(
+            create_new(&path, b"two", true).unwrap(),
+            dir.join("Note 1.md")
+        );
+        assert_eq!(
+            create_new(&path, b"three", false).unwrap(),
+            dir.join("Note 2.md")
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+        assert_eq!(fs::read_to_string(dir.join("Note 1.md")).unwrap(), "two");
+        assert!(!create_exact(&path, b"four", true).unwrap());
+        assert!(create_exact(&dir.join("Other.md"), b"four", false).unwrap());
+        fs::remove_file(dir.join("Other.md")).unwrap();
+
+        write(&path, b"replaced", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "replaced");
+        // No temp files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn create_without_hard_links() {
+        let dir = env::temp_dir().join("obsidian-rust-cli-atomic-unlinked");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Note.md");
+
+        let create = |path: &Path, contents: &[u8]| create_unlinked(path, contents, false, 2);
+        assert_eq!(create(&path, b"one").unwrap(), Some(path.clone()));
+        assert_eq!(create(&path, b"two").unwrap(), Some(dir.join("Note 1.md")));
+        assert_eq!(create(&path, b"three").unwrap(), None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+        assert_eq!(fs::read_to_string(dir.join("Note 1.md")).unwrap(), "two");
+        assert!(create(&dir.join("missing/Note.md"), b"").is_err());
+
+        // FAT refuses links with EPERM, others with EOPNOTSUPP
+        let failed = |kind| {
+            Err::<(), _>(io::Error::from(kind))
+                .context("Could not create")
+                .unwrap_err()
+        };
+        assert!(no_hard_links(&failed(ErrorKind::PermissionDenied)));
+        assert!(no_hard_links(&failed(ErrorKind::Unsupported)));
+        assert!(!no_hard_links(&failed(ErrorKind::NotFound)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn transaction_all_or_nothing() {
+        let dir = env::temp_dir().join("obsidian-rust-cli-transaction");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "a").unwrap();
+        fs::write(dir.join("b.md"), "b").unwrap();
+
+        // The second move fails, so the write and the first move are undone
+        let mut tx = Transaction::new(false);
+        tx.write(dir.join("a.md"), "changed");
+        tx.rename(dir.join("a.md"), dir.join("sub/a.md"));
+        tx.rename(dir.join("missing.md"), dir.join("c.md"));
+        assert!(tx.commit().is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.md")).unwrap(), "a");
+        assert!(!dir.join("sub").exists());
+
+        let mut tx = Transaction::new(true);
+        tx.rename(dir.join("a.md"), dir.join("b.md"));
+        assert!(tx.commit().is_err());
+
+        let mut tx = Transaction::new(true);
+        tx.write(dir.join("a.md"), "changed");
+        tx.write(dir.join("b.md"), "also");
+        tx.rename(dir.join("a.md"), dir.join("sub/a.md"));
+        tx.commit().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("sub/a.md")).unwrap(), "changed");
+        assert_eq!(fs::read_to_string(dir.join("b.md")).unwrap(), "also");
+        // b.md and sub/, no temp files
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = env::temp_dir().join("obsidian-rust-cli-atomic-links");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("real.md");
+        fs::write(&real, "old").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&real, dir.join("link.md")).unwrap();
+
+        write(&dir.join("link.md"), b"new", false).unwrap();
+        assert!(
+            fs::symlink_metadata(dir.join("link.md"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new");
+        let mode = |path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&real), 0o640);
+
+        let mut tx = Transaction::new(false);
+        tx.write(dir.join("link.md"), "again");
+        tx.commit().unwrap();
+        assert!(
+            fs::symlink_metadata(dir.join("link.md"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(&real).unwrap(), "again");
+        assert_eq!(mode(&real), 0o640);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,104 +1,693 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Local, NaiveDateTime};
 use std::{
+    collections::BTreeMap,
     env,
-    fs::{self, File},
-    io::Read,
+    fmt::Display,
+    fs,
     path::{Path, PathBuf},
 };
-use toml::Table;
 
-use crate::template::Template;
+use crate::{
+    atomic,
+    config_file::{
+        ConfigFile, DAILY_FOLDER_KEY, DEFAULT_PROFILE_KEY, FSYNC_KEY, INBOX_FOLDER_KEY,
+        NOTE_NAME_KEY, OPEN_WITH_KEY, TEMPLATE_KEY, TEMPLATES_FOLDER_KEY, VAULT_KEY,
+    },
+    editor::OpenWith,
+    index::{INDEX_DIR, IndexCache},
+    naming::{self, NameVars},
+    note::{Insert, Note, note_file, stays_inside},
+    obsidian::{DEFAULT_DAILY_FORMAT, OBSIDIAN_DIR, ObsidianSettings},
+    template::{Context, DEFAULT_TEMPLATE, Library, Template},
+    vault::{Refresh, Vault},
+};
+
+pub const APP_NAME: &str = "obsidian-rust-cli";
+pub const CONFIG_FILE: &str = "config.toml";
+pub const TEMPLATE_FILE: &str = "default_template.md";
+pub const DEFAULT_NOTE_NAME: &str = "Note_%Y_%m_%d_%H_%M_%S";
+
+// Resolved values that only come from Obsidian's own settings
+pub const DAILY_FORMAT_KEY: &str = "daily_format";
+pub const DAILY_TEMPLATE_KEY: &str = "daily_template";
+pub const ATTACHMENT_FOLDER_KEY: &str = "attachment_folder";
+
+/// Where a resolved config value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Flag,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "file {}", path.display()),
+            Source::Env(var) => write!(f, "env {var}"),
+            Source::Flag => write!(f, "flag"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Config {
     pub vault: PathBuf,
     pub template: Template,
+    pub templates_folder: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub daily_folder: PathBuf,
+    pub daily_format: String,
+    pub daily_template: Option<Template>,
+    pub new_note_folder: PathBuf,
+    /// Obsidian's attachment folder setting, `./`-relative ones are below
+    /// each note's folder
+    pub attachment_folder: String,
+    pub note_name: String,
+    pub fsync: bool,
+    pub open_with: OpenWith,
+    pub obsidian: ObsidianSettings,
+    pub sources: BTreeMap<&'static str, Source>,
 }
 
 impl Config {
-    pub fn build(vault: Option<PathBuf>, template: Option<PathBuf>) -> Result<Self> {
-        let mut cfg = Config {
-            vault: if let Some(vault) = &vault {
-                vault.to_path_buf()
-            } else if let Result::Ok(vault) = env::var("VAULT_PATH") {
-                PathBuf::from(vault)
-            } else {
-                let mut path = get_config_path();
-                path.push("default.toml");
-                Self::parse_cfg_file(path)?
-            },
-            ..Default::default()
-        };
+    /// Resolves the config in layers, later ones overriding earlier ones:
+    /// the user config dir, the `--config` file, env vars and finally flags.
+    /// Within each file the selected profile overrides the top-level keys.
+    /// A starter config is only written when there is neither a config nor
+    /// a `--vault` to go on.
+    pub fn build(
+        vault: Option<PathBuf>,
+        template: Option<PathBuf>,
+        config: Option<PathBuf>,
+        profile: Option<String>,
+    ) -> Result<Self> {
+        let user_cfg = config_file_path()?;
+        let mut layers = vec![];
 
-        if !cfg.is_valid_vault()? {
-            return Err(anyhow::Error::msg("Invalid vault"));
+        if user_cfg.is_file() {
+            layers.push(user_cfg);
+        } else if config.is_none() && vault.is_none() {
+            layers.push(bootstrap(user_cfg, false)?.path);
+        }
+        if let Some(config) = config {
+            layers.push(config);
         }
 
-        cfg.template.path = if let Some(templ) = &template {
-            templ.to_path_buf()
+        Self::from_layers(&layers, vault, template, profile, |var| env::var(var).ok())
+    }
+
+    /// `env` looks up environment variables
+    fn from_layers(
+        layers: &[PathBuf],
+        vault: Option<PathBuf>,
+        template: Option<PathBuf>,
+        profile: Option<String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let files = layers
+            .iter()
+            .map(|layer| ConfigFile::load(layer.to_path_buf()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut cfg = Config::default();
+
+        if let Some(profile) = profile {
+            cfg.select_profile(profile, Source::Flag);
+        } else if let Some(profile) = env("VAULT_PROFILE") {
+            cfg.select_profile(profile, Source::Env("VAULT_PROFILE"));
         } else {
-            let mut path = get_config_path();
-            path.push("default_template.md");
-            path
+            for file in &files {
+                if let Some(profile) = file.get(None, DEFAULT_PROFILE_KEY)? {
+                    cfg.select_profile(profile, Source::File(file.path.clone()));
+                }
+            }
+        }
+
+        let mut vault_path = None;
+        let mut templ_path = None;
+        let mut daily_folder = None;
+        let mut note_name = None;
+        let mut inbox_folder = None;
+        let mut templates_folder = None;
+        let mut fsync = None;
+        let mut open_with = None;
+        let mut profile_found = false;
+
+        for file in &files {
+            let mut tables = vec![None];
+            if let Some(profile) = cfg.profile.as_deref().filter(|p| file.has_profile(p)) {
+                profile_found = true;
+                tables.push(Some(profile));
+            }
+            for table in tables {
+                let source = || Source::File(file.path.clone());
+                if let Some(val) = file.get_path(table, VAULT_KEY)? {
+                    vault_path = Some((val, source()));
+                }
+                if let Some(val) = file.get_path(table, TEMPLATE_KEY)? {
+                    templ_path = Some((val, source()));
+                }
+                if let Some(val) = file
+                    .get(table, DAILY_FOLDER_KEY)?
+                    .filter(|val| !val.is_empty())
+                {
+                    daily_folder = Some((PathBuf::from(val), source()));
+                }
+                if let Some(val) = file.get(table, NOTE_NAME_KEY)? {
+                    note_name = Some((val, source()));
+                }
+                if let Some(val) = file
+                    .get(table, INBOX_FOLDER_KEY)?
+                    .filter(|val| !val.is_empty())
+                {
+                    inbox_folder = Some((PathBuf::from(val), source()));
+                }
+                if let Some(val) = file.get_path(table, TEMPLATES_FOLDER_KEY)? {
+                    templates_folder = Some((val, source()));
+                }
+                if let Some(val) = file.get(table, FSYNC_KEY)? {
+                    fsync = Some((val == "true", source()));
+                }
+                if let Some(val) = file
+                    .get(table, OPEN_WITH_KEY)?
+                    .filter(|val| !val.is_empty())
+                {
+                    open_with = Some((val.parse()?, source()));
+                }
+            }
+        }
+
+        if let Some(profile) = cfg.profile.as_ref().filter(|_| !profile_found) {
+            return Err(anyhow::Error::msg(format!("Unknown profile: {profile}")));
+        }
+
+        if let Some(vault) = env("VAULT_PATH") {
+            vault_path = Some((expand_path(&vault), Source::Env("VAULT_PATH")));
+        }
+        if let Some(templ) = env("VAULT_TEMPLATE") {
+            templ_path = Some((expand_path(&templ), Source::Env("VAULT_TEMPLATE")));
+        }
+        if let Some(vault) = vault {
+            vault_path = Some((vault, Source::Flag));
+        }
+        if let Some(templ) = template {
+            templ_path = Some((templ, Source::Flag));
+        }
+
+        let (vault, source) = vault_path.with_context(|| {
+            format!(
+                "No vault configured, set `{VAULT_KEY}` in {} or pass --vault",
+                layers
+                    .last()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default()
+            )
+        })?;
+        cfg.vault = vault;
+        cfg.sources.insert(VAULT_KEY, source);
+
+        if !Self::is_valid_vault(&cfg.vault)? {
+            return Err(anyhow::Error::msg("Invalid vault"));
+        }
+
+        match templ_path {
+            Some((templ, source)) => {
+                cfg.template.path = templ;
+                cfg.sources.insert(TEMPLATE_KEY, source);
+                if !cfg.template.path.is_file() {
+                    return Err(anyhow::Error::msg("Invalid templ path"));
+                }
+                cfg.template.parse_string()?;
+            }
+            None => {
+                // Without a starter config there may be no default template yet
+                cfg.template.path = config_dir()?.join(TEMPLATE_FILE);
+                cfg.sources.insert(TEMPLATE_KEY, Source::Default);
+                match cfg.template.path.is_file() {
+                    true => cfg.template.parse_string()?,
+                    false => cfg.template.template = DEFAULT_TEMPLATE.to_string(),
+                }
+            }
+        }
+
+        cfg.obsidian = ObsidianSettings::load(&cfg.vault)?;
+
+        if let Some((folder, source)) = templates_folder.or_else(|| {
+            let folder = cfg.vault.join(cfg.obsidian.templates_folder()?);
+            Some((
+                folder,
+                Source::File(cfg.obsidian.settings_file("templates.json")),
+            ))
+        }) {
+            cfg.templates_folder = Some(folder);
+            cfg.sources.insert(TEMPLATES_FOLDER_KEY, source);
+        }
+        let include_dir = cfg.templates_folder.clone();
+        cfg.template.include_dir = include_dir.clone();
+        let daily_notes = || Source::File(cfg.obsidian.daily_settings_file());
+
+        let (folder, source) = daily_folder
+            .or_else(|| cfg.obsidian.daily_folder().map(|f| (f, daily_notes())))
+            .unwrap_or((PathBuf::new(), Source::Default));
+        cfg.daily_folder = folder;
+        cfg.sources.insert(DAILY_FOLDER_KEY, source);
+
+        let (format, source) = match cfg.obsidian.daily_format() {
+            Some(format) => (format.to_string(), daily_notes()),
+            None => (DEFAULT_DAILY_FORMAT.to_string(), Source::Default),
         };
+        cfg.daily_format = format;
+        cfg.sources.insert(DAILY_FORMAT_KEY, source);
 
-        if !cfg.template.path.is_file() {
-            return Err(anyhow::Error::msg("Invalid templ path"));
+        if let Some(templ) = cfg.obsidian.daily_template() {
+            let mut daily_template = Template {
+                path: cfg.vault.join(templ),
+                include_dir,
+                ..Default::default()
+            };
+            if daily_template.path.is_file() {
+                daily_template.parse_string()?;
+                cfg.daily_template = Some(daily_template);
+                cfg.sources.insert(DAILY_TEMPLATE_KEY, daily_notes());
+            }
         }
-        cfg.template.parse_string()?;
+
+        let (folder, source) = inbox_folder
+            .or_else(|| {
+                let folder = cfg.obsidian.new_note_folder()?;
+                Some((folder, Source::File(cfg.obsidian.settings_file("app.json"))))
+            })
+            .unwrap_or((PathBuf::new(), Source::Default));
+        cfg.new_note_folder = folder;
+        cfg.sources.insert(INBOX_FOLDER_KEY, source);
+
+        let (folder, source) = match cfg.obsidian.app.attachment_folder_path.clone() {
+            Some(folder) => (folder, Source::File(cfg.obsidian.settings_file("app.json"))),
+            None => (String::new(), Source::Default),
+        };
+        cfg.attachment_folder = folder;
+        cfg.sources.insert(ATTACHMENT_FOLDER_KEY, source);
+
+        let (name, source) = note_name.unwrap_or((DEFAULT_NOTE_NAME.to_string(), Source::Default));
+        cfg.note_name = name;
+        cfg.sources.insert(NOTE_NAME_KEY, source);
+
+        let (fsync, source) = fsync.unwrap_or((false, Source::Default));
+        cfg.fsync = fsync;
+        cfg.sources.insert(FSYNC_KEY, source);
+
+        let (open_with, source) = open_with.unwrap_or((OpenWith::default(), Source::Default));
+        cfg.open_with = open_with;
+        cfg.sources.insert(OPEN_WITH_KEY, source);
 
         Ok(cfg)
     }
 
-    pub fn get_full_path(&self, note_path: &PathBuf) -> Result<PathBuf> {
-        let mut abs_path = self.vault.clone();
-        abs_path.push(note_path);
-        if !abs_path.is_file() {
-            return Err(anyhow::Error::msg("Invalid note path, not a note"));
+    fn select_profile(&mut self, profile: String, source: Source) {
+        self.profile = Some(profile);
+        self.sources.insert(DEFAULT_PROFILE_KEY, source);
+    }
+
+    /// A template context with the date and time formats of Obsidian's
+    /// templates plugin
+    pub fn template_context(&self, now: NaiveDateTime, title: &str) -> Context {
+        let mut context = Context::new(now);
+        let templates = &self.obsidian.templates;
+        if let Some(format) = templates.date_format.as_ref().filter(|f| !f.is_empty()) {
+            context.date_format = format.clone();
+        }
+        if let Some(format) = templates.time_format.as_ref().filter(|f| !f.is_empty()) {
+            context.time_format = format.clone();
+        }
+        context.insert("title", title);
+        context
+    }
+
+    /// The templates folder, with includes resolved against it
+    pub fn library(&self) -> Result<Library> {
+        let dir = self.templates_folder.clone().with_context(|| {
+            format!("No templates folder configured, set `{TEMPLATES_FOLDER_KEY}` or Obsidian's templates folder")
+        })?;
+        Ok(Library::new(dir.clone(), Some(dir)))
+    }
+
+    /// `--template <name>` from the library, or the configured template
+    pub fn select_template(&self, name: Option<&str>) -> Result<Template> {
+        match name {
+            Some(name) => self.library()?.get(name),
+            None => Ok(self.template.clone()),
+        }
+    }
+
+    /// Inserts `text` into a vault-relative note. A missing note is created
+    /// from the configured template, with its folders, unless `create` is
+    /// false. Returns the note and whether it was created.
+    pub fn append_to_note(
+        &self,
+        path: &Path,
+        text: &str,
+        at: &Insert,
+        create: bool,
+    ) -> Result<(Note, bool)> {
+        let path = note_file(path);
+        if self.vault.join(&path).is_file() {
+            let mut note = Note::load(&self.vault, &path)?.with_fsync(self.fsync);
+            note.insert(text, at)?;
+            note.save()?;
+            return Ok((note, false));
         }
-        Ok(abs_path.to_path_buf())
+        if !create {
+            return Err(anyhow::Error::msg(format!(
+                "Note does not exist: {}",
+                path.display()
+            )));
+        }
+
+        let title = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut context = self.template_context(Local::now().naive_local(), &title);
+        // Plain appends fill `{{body}}`, anything else goes where it was asked
+        let plain = *at == Insert::default();
+        context.insert("body", if plain { text } else { "" });
+        let mut note = Note::new(self.vault.join(&path), self.template.render(&context)?)
+            .with_fsync(self.fsync);
+        if !plain {
+            note.insert(text, at)?;
+        }
+        note.create()?;
+        Ok((note, true))
     }
 
-    fn is_valid_vault(&mut self) -> Result<bool> {
-        let mut paths = fs::read_dir(self.vault.as_path())?;
-        Ok(self.vault.is_dir()
-            && paths.any(|path_result| path_result.unwrap().file_name() == ".obsidian"))
+    /// Absolute path of a note created from `templ` at `now`. `folder`, then
+    /// the template's `folder`, override `new_note_folder`, and the
+    /// template's `filename` overrides `note_name`.
+    pub fn new_note_path(
+        &self,
+        templ: &Template,
+        now: &DateTime<Local>,
+        folder: Option<&Path>,
+        vars: &NameVars,
+    ) -> Result<PathBuf> {
+        let meta = templ.meta();
+        let folder = folder.or(meta.folder.as_deref());
+        if let Some(folder) = folder.filter(|folder| !stays_inside(folder)) {
+            return Err(anyhow::Error::msg(format!(
+                "Folder {} is not inside the vault",
+                folder.display()
+            )));
+        }
+        let folder = folder.unwrap_or(&self.new_note_folder);
+        let pattern = meta.filename.as_deref().unwrap_or(&self.note_name);
+
+        let name = naming::render(pattern, now.naive_local(), vars)?;
+        Ok(self.vault.join(folder).join(format!("{name}.md")))
     }
 
-    fn parse_cfg_file(path: PathBuf) -> Result<PathBuf> {
-        let mut file = File::open(path.as_path())?;
-        let mut buf: String = String::new();
-        file.read_to_string(&mut buf)?;
+    /// Indexes the vault, leaving out Obsidian's excluded folders. Only
+    /// notes changed since the last run are parsed.
+    pub fn index(&self) -> Result<Vault> {
+        Ok(self.refresh_index(true)?.0)
+    }
 
-        let vault_path = buf.parse::<Table>()?;
-        let vault_path = vault_path["vault_path"].as_str().unwrap_or("");
-        let vault_path = vault_path.replace("~", env!("WORKSPACE_ROOT"));
+    /// Brings the saved index up to date with the vault, saving it again
+    /// when `save` is set and something changed
+    pub fn refresh_index(&self, save: bool) -> Result<(Vault, Refresh)> {
+        let cache = self.index_cache()?;
+        let cached = cache.read(&self.vault)?.unwrap_or_default();
+        let (vault, refresh) =
+            Vault::refresh(&self.vault, &self.obsidian.excluded_folders(), &cached)?;
+        if save && refresh.changed() {
+            cache.write(&vault)?;
+        }
+        Ok((vault, refresh))
+    }
 
-        Ok(PathBuf::from(vault_path))
+    pub fn index_cache(&self) -> Result<IndexCache> {
+        Ok(IndexCache::for_vault(
+            &cache_dir()?.join(INDEX_DIR),
+            &self.vault,
+        ))
     }
+
+    /// Vault-relative path of a note given by its path, or by a title or
+    /// alias only one note has. Existing paths are taken without indexing.
+    pub fn find_note(&self, query: &str) -> Result<PathBuf> {
+        let path = note_file(Path::new(query));
+        if self.vault.join(&path).is_file() {
+            return Ok(path);
+        }
+        Ok(self.index()?.find(query)?.path.clone())
+    }
+
+    pub fn is_valid_vault(vault: &Path) -> Result<bool> {
+        if !vault.is_dir() {
+            return Ok(false);
+        }
+        for entry in fs::read_dir(vault)? {
+            if entry?.file_name() == OBSIDIAN_DIR {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Writes a starter config to `path` and the default template next to it
+pub fn bootstrap(path: PathBuf, force: bool) -> Result<ConfigFile> {
+    let file = ConfigFile::init(path, force)?;
+    if let Some(dir) = file.path.parent() {
+        let templ_path = dir.join(TEMPLATE_FILE);
+        if !templ_path.exists() {
+            atomic::create_new(&templ_path, DEFAULT_TEMPLATE.as_bytes(), false)?;
+        }
+    }
+    Ok(file)
+}
+
+/// Expands a leading `~` to the user's home directory
+pub fn expand_path(path: &str) -> PathBuf {
+    let Some(rest) = path.strip_prefix('~') else {
+        return PathBuf::from(path);
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) if rest.starts_with(['/', '\\']) => home.join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/obsidian-rust-cli`, or the platform's config dir
+pub fn config_dir() -> Result<PathBuf> {
+    if let Some(xdg) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(xdg).join(APP_NAME));
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_NAME))
+        .context("Could not find the user config directory")
+}
+
+/// `$XDG_CACHE_HOME/obsidian-rust-cli`, or the platform's cache dir
+pub fn cache_dir() -> Result<PathBuf> {
+    if let Some(xdg) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(xdg).join(APP_NAME));
+    }
+    dirs::cache_dir()
+        .map(|dir| dir.join(APP_NAME))
+        .context("Could not find the user cache directory")
 }
 
-fn get_config_path() -> PathBuf {
-    let workspace_root = env!("WORKSPACE_ROOT");
-    Path::new(workspace_root).join("config")
+pub fn config_file_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(CONFIG_FILE))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{Config, get_config_path};
+    use anyhow::Result;
+    use chrono::{Local, TimeZone};
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use crate::{
+        config::{Config, DEFAULT_NOTE_NAME, Source, expand_path},
+        config_file::{DAILY_FOLDER_KEY, DEFAULT_PROFILE_KEY, NOTE_NAME_KEY, VAULT_KEY},
+        naming::NameVars,
+        note::Insert,
+        template::Template,
+    };
+
+    fn workspace_config() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../config")
+    }
+
+    /// Layers without `VAULT_PATH` and the like from the test's environment
+    fn from_layers(
+        layers: &[PathBuf],
+        vault: Option<PathBuf>,
+        template: Option<PathBuf>,
+        profile: Option<String>,
+    ) -> Result<Config> {
+        Config::from_layers(layers, vault, template, profile, |_| None)
+    }
+
+    /// A fresh temp dir of this process, so parallel runs don't collide
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("obsidian-rust-cli-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
     #[test]
     fn default_build() {
-        let cfg = Config::build(None, None).unwrap();
+        let cfg_path = workspace_config().join("default.toml");
+        let cfg = from_layers(&[cfg_path], None, None, None).unwrap();
+
+        assert_eq!(
+            cfg.template.path,
+            workspace_config().join("default_template.md")
+        );
+        assert_eq!(cfg.vault, workspace_config().join("."));
+    }
 
-        let mut templ_path = get_config_path();
-        templ_path.push("default_template.md");
+    #[test]
+    fn flags_override_layers() {
+        let cfg_path = workspace_config().join("default.toml");
+        let templ = workspace_config().join("default_template.md");
+        let cfg = from_layers(
+            &[cfg_path],
+            Some(workspace_config()),
+            Some(templ.clone()),
+            None,
+        )
+        .unwrap();
 
-        assert_eq!(cfg.template.path, templ_path);
+        assert_eq!(cfg.vault, workspace_config());
+        assert_eq!(cfg.template.path, templ);
+        assert_eq!(cfg.sources[VAULT_KEY], Source::Flag);
+        assert_eq!(cfg.sources[NOTE_NAME_KEY], Source::Default);
+    }
 
-        let mut cfg_path = get_config_path();
-        cfg_path.push("default.toml");
+    #[test]
+    fn profile_overrides_top_level() {
+        let layers = [workspace_config().join("default.toml")];
+        let cfg = from_layers(&layers, None, None, Some("test".into())).unwrap();
 
-        assert_eq!(cfg.vault, Config::parse_cfg_file(cfg_path).unwrap());
+        assert_eq!(cfg.profile.as_deref(), Some("test"));
+        assert_eq!(cfg.daily_folder, PathBuf::from("daily"));
+        assert_eq!(cfg.note_name, DEFAULT_NOTE_NAME);
+        assert_eq!(cfg.sources[DEFAULT_PROFILE_KEY], Source::Flag);
+        assert_eq!(
+            cfg.sources[DAILY_FOLDER_KEY],
+            Source::File(layers[0].clone())
+        );
+
+        assert!(from_layers(&layers, None, None, Some("missing".into())).is_err());
+
+        let env = |var: &str| (var == "VAULT_PROFILE").then(|| "missing".to_string());
+        assert!(Config::from_layers(&layers, None, None, None, env).is_err());
+    }
+
+    #[test]
+    fn append_creates_missing_notes() {
+        let vault = temp_dir("append");
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        let mut cfg = Config {
+            vault: vault.clone(),
+            ..Default::default()
+        };
+        cfg.template.template = "# {{title}}\n{{body}}\n".to_string();
+
+        let path = Path::new("inbox/ideas");
+        assert!(
+            cfg.append_to_note(path, "one", &Insert::default(), false)
+                .is_err()
+        );
+        let (note, created) = cfg
+            .append_to_note(path, "one", &Insert::default(), true)
+            .unwrap();
+        assert!(created);
+        assert_eq!(note.path, vault.join("inbox/ideas.md"));
+
+        let at = Insert {
+            heading: Some("Log".into()),
+            ..Default::default()
+        };
+        let (note, created) = cfg.append_to_note(path, "two", &at, false).unwrap();
+        assert!(!created);
+        assert_eq!(
+            fs::read_to_string(&note.path).unwrap(),
+            "# ideas\none\n\n## Log\ntwo\n"
+        );
+        fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn new_note_paths() {
+        let cfg = Config {
+            vault: PathBuf::from("vault"),
+            new_note_folder: PathBuf::from("Inbox"),
+            note_name: "{{date:YYYYMMDD}} {{title}}".to_string(),
+            ..Default::default()
+        };
+        let now = Local.with_ymd_and_hms(2025, 8, 15, 9, 0, 0).unwrap();
+        let idea = NameVars {
+            title: None,
+            idea: "Fix #42: the [[parser]]",
+        };
+        assert_eq!(
+            cfg.new_note_path(&Template::default(), &now, None, &idea)
+                .unwrap(),
+            PathBuf::from("vault/Inbox/20250815 Fix 42- the parser.md")
+        );
+
+        let templ = Template {
+            template: "---\ntemplate-folder: Meetings\ntemplate-filename: \"{{id}}\"\n---\n"
+                .to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            cfg.new_note_path(&templ, &now, None, &idea).unwrap(),
+            PathBuf::from("vault/Meetings/20250815090000.md")
+        );
+        assert_eq!(
+            cfg.new_note_path(&templ, &now, Some(Path::new("Projects")), &idea)
+                .unwrap(),
+            PathBuf::from("vault/Projects/20250815090000.md")
+        );
+
+        let escaping = Template {
+            template: "---\ntemplate-folder: ../../.ssh\n---\n".to_string(),
+            ..Default::default()
+        };
+        assert!(cfg.new_note_path(&escaping, &now, None, &idea).is_err());
+        for folder in ["../out", "/tmp"] {
+            assert!(
+                cfg.new_note_path(&templ, &now, Some(Path::new(folder)), &idea)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_vault() {
+        assert!(!Config::is_valid_vault(&workspace_config().join("missing")).unwrap());
+        assert!(Config::is_valid_vault(&workspace_config()).unwrap());
+        let dir = temp_dir("not-a-vault");
+        assert!(!Config::is_valid_vault(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tilde_expansion() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_path("~"), home);
+        assert_eq!(expand_path("~/notes"), home.join("notes"));
+        assert_eq!(expand_path("a/~/b"), PathBuf::from("a/~/b"));
+        assert_eq!(expand_path("~user"), PathBuf::from("~user"));
     }
 }
@@ -0,0 +1,340 @@
+use anyhow::{Context, Result};
+use std::{fs, path::PathBuf};
+use toml_edit::{DocumentMut, Item, Table, TableLike, value};
+
+use crate::{
+    atomic,
+    config::{Config, expand_path},
+    editor::OpenWith,
+    naming,
+};
+
+pub const VAULT_KEY: &str = "vault_path";
+pub const TEMPLATE_KEY: &str = "template_path";
+pub const DAILY_FOLDER_KEY: &str = "daily_folder";
+pub const NOTE_NAME_KEY: &str = "note_name";
+pub const INBOX_FOLDER_KEY: &str = "inbox_folder";
+pub const TEMPLATES_FOLDER_KEY: &str = "templates_folder";
+pub const FSYNC_KEY: &str = "fsync";
+pub const OPEN_WITH_KEY: &str = "open_with";
+pub const DEFAULT_PROFILE_KEY: &str = "default_profile";
+pub const PROFILES_KEY: &str = "profiles";
+
+/// Keys that can be set both at the top level and inside a `[profiles.<name>]` table
+pub const PROFILE_KEYS: [&str; 8] = [
+    VAULT_KEY,
+    TEMPLATE_KEY,
+    DAILY_FOLDER_KEY,
+    NOTE_NAME_KEY,
+    INBOX_FOLDER_KEY,
+    TEMPLATES_FOLDER_KEY,
+    FSYNC_KEY,
+    OPEN_WITH_KEY,
+];
+
+const STARTER_CONFIG: &str = "\
+# Path to the Obsidian vault (must contain a .obsidian folder).
+# Relative paths are resolved against this file's directory, `~` is home.
+vault_path = \"\"
+
+# Path to the note template
+template_path = \"default_template.md\"
+
+# Folder of templates for `new --template <name>`.
+# Empty uses the folder from Obsidian's templates settings.
+templates_folder = \"\"
+
+# Folder for daily notes, relative to the vault root.
+# Empty uses the folder from Obsidian's daily notes settings.
+daily_folder = \"\"
+
+# File name of new notes (without `.md`). Either a chrono format string,
+# or a pattern with `{{date:YYYYMMDDHHmm}}`, `{{time}}`, `{{title}}`,
+# `{{slug}}` (the idea's first line) and `{{id}}` (a Zettelkasten ID),
+# e.g. \"{{date:YYYYMMDDHHmm}} {{title}}\"
+note_name = \"Note_%Y_%m_%d_%H_%M_%S\"
+
+# Folder for new notes, relative to the vault root.
+# Empty uses the folder from Obsidian's \"Default location for new notes\".
+inbox_folder = \"\"
+
+# Flush every note write to disk before returning. Slower, but safer
+# when the vault is synced while notes are being written.
+fsync = false
+
+# How `open` and `periodic` open notes: \"obsidian\", \"editor\" ($VISUAL or
+# $EDITOR) or any command, e.g. \"code --wait\"
+open_with = \"obsidian\"
+
+# Profile used when neither --profile nor VAULT_PROFILE is given.
+# Profiles override the keys above, e.g.
+#
+# [profiles.work]
+# vault_path = \"~/notes/work\"
+# daily_folder = \"Journal\"
+";
+
+/// The TOML config file as a document that keeps comments and formatting
+/// intact, so `config set` only touches the value it was asked to change.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigFile {
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let buf = fs::read_to_string(path.as_path())
+            .with_context(|| format!("Could not read config file {}", path.display()))?;
+        let doc = buf.parse::<DocumentMut>()?;
+        Ok(Self { path, doc })
+    }
+
+    /// Writes a starter config to `path`. Refuses to overwrite unless `force`.
+    pub fn init(path: PathBuf, force: bool) -> Result<Self> {
+        if path.exists() && !force {
+            return Err(anyhow::Error::msg(format!(
+                "Config file already exists: {}",
+                path.display()
+            )));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = Self {
+            path,
+            doc: STARTER_CONFIG.parse::<DocumentMut>()?,
+        };
+        file.save()?;
+        Ok(file)
+    }
+
+    /// Reads `key` from the top level, or from the named profile's table.
+    /// Booleans are read as `true`/`false`.
+    pub fn get(&self, profile: Option<&str>, key: &str) -> Result<Option<String>> {
+        check_key(profile, key)?;
+        Ok(self
+            .table(profile)
+            .and_then(|table| table.get(key))
+            .and_then(as_string))
+    }
+
+    /// Like `get`, but as a path relative to the file. Empty values are unset.
+    pub fn get_path(&self, profile: Option<&str>, key: &str) -> Result<Option<PathBuf>> {
+        Ok(self
+            .get(profile, key)?
+            .filter(|val| !val.is_empty())
+            .map(|val| self.resolve(&val)))
+    }
+
+    pub fn has_profile(&self, profile: &str) -> bool {
+        self.table(Some(profile)).is_some()
+    }
+
+    pub fn profiles(&self) -> Vec<String> {
+        self.doc
+            .get(PROFILES_KEY)
+            .and_then(Item::as_table_like)
+            .map(|profiles| profiles.iter().map(|(name, _)| name.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Sets `key` after validating the value, keeping the key's comments.
+    /// A missing profile table is created.
+    pub fn set(&mut self, profile: Option<&str>, key: &str, val: &str) -> Result<()> {
+        check_key(profile, key)?;
+        let path = self.resolve(val);
+        match key {
+            VAULT_KEY if !Config::is_valid_vault(&path)? => {
+                return Err(anyhow::Error::msg("Invalid vault"));
+            }
+            TEMPLATE_KEY if !val.is_empty() && !path.is_file() => {
+                return Err(anyhow::Error::msg("Invalid templ path"));
+            }
+            TEMPLATES_FOLDER_KEY if !val.is_empty() && !path.is_dir() => {
+                return Err(anyhow::Error::msg("Invalid templates folder"));
+            }
+            NOTE_NAME_KEY => naming::validate(val)?,
+            OPEN_WITH_KEY => {
+                val.parse::<OpenWith>()?;
+            }
+            DEFAULT_PROFILE_KEY if !self.has_profile(val) => {
+                return Err(anyhow::Error::msg(format!("Unknown profile: {val}")));
+            }
+            FSYNC_KEY if val.parse::<bool>().is_err() => {
+                return Err(anyhow::Error::msg("Invalid fsync, expected true or false"));
+            }
+            _ => {}
+        }
+
+        let new = match key {
+            FSYNC_KEY => value(val == "true"),
+            _ => value(val),
+        };
+        let table = self.table_mut(profile)?;
+        if let Some(old) = table.get_mut(key).and_then(Item::as_value_mut) {
+            let decor = old.decor().clone();
+            *old = new.into_value().expect("a value item");
+            *old.decor_mut() = decor;
+        } else {
+            table.insert(key, new);
+        }
+        Ok(())
+    }
+
+    /// Every string key, with profile keys as `profiles.<name>.<key>`
+    pub fn list(&self) -> Vec<(String, String)> {
+        let mut entries = vec![];
+        for (key, item) in self.doc.iter() {
+            if let Some(val) = as_string(item) {
+                entries.push((key.to_string(), val));
+            }
+        }
+        for name in self.profiles() {
+            let Some(table) = self.table(Some(&name)) else {
+                continue;
+            };
+            for (key, item) in table.iter() {
+                if let Some(val) = as_string(item) {
+                    entries.push((format!("{PROFILES_KEY}.{name}.{key}"), val));
+                }
+            }
+        }
+        entries
+    }
+
+    fn table(&self, profile: Option<&str>) -> Option<&dyn TableLike> {
+        match profile {
+            None => Some(self.doc.as_table()),
+            Some(name) => self.doc.get(PROFILES_KEY)?.get(name)?.as_table_like(),
+        }
+    }
+
+    fn table_mut(&mut self, profile: Option<&str>) -> Result<&mut dyn TableLike> {
+        let Some(name) = profile else {
+            return Ok(self.doc.as_table_mut());
+        };
+        self.doc
+            .entry(PROFILES_KEY)
+            .or_insert_with(|| {
+                let mut profiles = Table::new();
+                profiles.set_implicit(true);
+                Item::Table(profiles)
+            })
+            .as_table_like_mut()
+            .context("`profiles` is not a table")?
+            .entry(name)
+            .or_insert(Item::Table(Table::new()))
+            .as_table_like_mut()
+            .with_context(|| format!("Profile `{name}` is not a table"))
+    }
+
+    fn resolve(&self, val: &str) -> PathBuf {
+        let path = expand_path(val);
+        match self.path.parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        atomic::write(&self.path, self.doc.to_string().as_bytes(), false)
+    }
+}
+
+fn as_string(item: &Item) -> Option<String> {
+    item.as_str()
+        .map(str::to_string)
+        .or_else(|| item.as_bool().map(|val| val.to_string()))
+}
+
+fn check_key(profile: Option<&str>, key: &str) -> Result<()> {
+    let top_level = profile.is_none() && key == DEFAULT_PROFILE_KEY;
+    if !PROFILE_KEYS.contains(&key) && !top_level {
+        return Err(anyhow::Error::msg(format!(
+            "Unknown config key: {key} (expected one of: {})",
+            PROFILE_KEYS.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use crate::config_file::{
+        ConfigFile, DEFAULT_PROFILE_KEY, FSYNC_KEY, NOTE_NAME_KEY, OPEN_WITH_KEY, TEMPLATE_KEY,
+        VAULT_KEY,
+    };
+
+    fn temp_config(name: &str, contents: &str) -> ConfigFile {
+        let path = env::temp_dir().join(format!("obsidian-rust-cli-{name}.toml"));
+        fs::write(&path, contents).unwrap();
+        ConfigFile::load(path).unwrap()
+    }
+
+    #[test]
+    fn set_preserves_comments() {
+        let mut file = temp_config(
+            "set",
+            "# vault comment\nvault_path = \"\" # inline\n\n# templ comment\ntemplate_path = \"\"\n",
+        );
+        let vault = Path::new(env!("CARGO_MANIFEST_DIR")).join("../config");
+        let vault = vault.to_str().unwrap();
+        file.set(None, VAULT_KEY, vault).unwrap();
+        file.save().unwrap();
+
+        let saved = fs::read_to_string(&file.path).unwrap();
+        assert_eq!(
+            saved,
+            format!(
+                "# vault comment\nvault_path = \"{vault}\" # inline\n\n# templ comment\ntemplate_path = \"\"\n"
+            )
+        );
+        assert_eq!(file.get(None, VAULT_KEY).unwrap().as_deref(), Some(vault));
+        fs::remove_file(&file.path).unwrap();
+    }
+
+    #[test]
+    fn set_rejects_invalid_values() {
+        let mut file = temp_config("invalid", "vault_path = \"\"\n");
+        assert!(file.set(None, VAULT_KEY, "missing").is_err());
+        assert!(file.set(None, TEMPLATE_KEY, "missing.md").is_err());
+        assert!(file.set(None, NOTE_NAME_KEY, "Note_%Q").is_err());
+        assert!(file.set(None, NOTE_NAME_KEY, "{{date").is_err());
+        assert!(file.set(None, DEFAULT_PROFILE_KEY, "missing").is_err());
+        assert!(file.set(Some("work"), DEFAULT_PROFILE_KEY, "work").is_err());
+        assert!(file.set(None, FSYNC_KEY, "yes").is_err());
+        assert!(file.set(None, OPEN_WITH_KEY, "").is_err());
+        assert!(file.set(None, "unknown", "value").is_err());
+        assert!(file.get(None, "unknown").is_err());
+        fs::remove_file(&file.path).unwrap();
+    }
+
+    #[test]
+    fn set_creates_profile() {
+        let mut file = temp_config("profile", "vault_path = \"\"\n");
+        file.set(Some("work"), NOTE_NAME_KEY, "%Y%m%d").unwrap();
+        file.set(None, DEFAULT_PROFILE_KEY, "work").unwrap();
+        file.set(Some("work"), FSYNC_KEY, "true").unwrap();
+
+        assert_eq!(file.profiles(), vec!["work".to_string()]);
+        assert_eq!(
+            file.get(Some("work"), NOTE_NAME_KEY).unwrap().as_deref(),
+            Some("%Y%m%d")
+        );
+        assert_eq!(
+            file.list(),
+            vec![
+                ("vault_path".to_string(), "".to_string()),
+                ("default_profile".to_string(), "work".to_string()),
+                ("profiles.work.note_name".to_string(), "%Y%m%d".to_string()),
+                ("profiles.work.fsync".to_string(), "true".to_string()),
+            ]
+        );
+        assert!(file.doc.to_string().contains("fsync = true"));
+        assert!(file.doc.to_string().contains("[profiles.work]"));
+        fs::remove_file(&file.path).unwrap();
+    }
+}
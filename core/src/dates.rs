@@ -0,0 +1,133 @@
+use anyhow::Result;
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+/// Parses a date relative to `today`:
+///
+/// - `today`, `yesterday`, `tomorrow`
+/// - `+3d`, `-1w`, `+2m`, `-1y`, or `-2` for days
+/// - `last friday`, `next monday`, and `monday` for the next Monday or today
+/// - `last week`, `next month`... for one unit back or forward
+/// - ISO dates, `2026-10-01`
+pub fn parse(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    let invalid = || {
+        anyhow::Error::msg(format!(
+            "Invalid date `{input}`, expected e.g. today, yesterday, +3d, last friday or 2026-10-01"
+        ))
+    };
+
+    match input.as_str() {
+        "today" | "now" => return Ok(today),
+        "yesterday" => return today.pred_opt().ok_or_else(invalid),
+        "tomorrow" => return today.succ_opt().ok_or_else(invalid),
+        _ => {}
+    }
+    if let Result::Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    if let Some(sign) = input.chars().next().filter(|c| matches!(c, '+' | '-')) {
+        let (num, unit) = match input[1..].find(|c: char| !c.is_ascii_digit()) {
+            Some(end) => input[1..].split_at(end),
+            None => (&input[1..], "d"),
+        };
+        let num = num.parse::<i64>().map_err(|_| invalid())?;
+        let offset = if sign == '-' { -num } else { num };
+        return shift(today, offset, unit.trim()).ok_or_else(invalid);
+    }
+
+    let (direction, rest) = match input.split_once(' ') {
+        Some(("last", rest)) => (-1, rest.trim()),
+        Some(("next", rest)) => (1, rest.trim()),
+        Some(_) => return Err(invalid()),
+        None => (0, input.as_str()),
+    };
+    if let Result::Ok(weekday) = rest.parse::<Weekday>() {
+        return Ok(nearest(today, weekday, direction));
+    }
+    match direction {
+        0 => Err(invalid()),
+        _ => shift(today, direction, rest).ok_or_else(invalid),
+    }
+}
+
+/// `date` moved by `offset` days, weeks, months or years
+fn shift(date: NaiveDate, offset: i64, unit: &str) -> Option<NaiveDate> {
+    let months = |months: i64| {
+        let n = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        match months < 0 {
+            true => date.checked_sub_months(n),
+            false => date.checked_add_months(n),
+        }
+    };
+    let days = match unit {
+        "d" | "day" | "days" => offset,
+        "w" | "week" | "weeks" => offset.checked_mul(7)?,
+        "m" | "month" | "months" => return months(offset),
+        "y" | "year" | "years" => return months(offset.checked_mul(12)?),
+        _ => return None,
+    };
+    let n = Days::new(days.unsigned_abs());
+    match days < 0 {
+        true => date.checked_sub_days(n),
+        false => date.checked_add_days(n),
+    }
+}
+
+/// The closest `weekday` before (-1) or after (1) `today`, or on or after it (0)
+fn nearest(today: NaiveDate, weekday: Weekday, direction: i64) -> NaiveDate {
+    let from = today.weekday().num_days_from_monday();
+    let to = weekday.num_days_from_monday();
+    match direction {
+        ..0 => today - Days::new(((from + 7 - to - 1) % 7 + 1).into()),
+        0 => today + Days::new(((to + 7 - from) % 7).into()),
+        _ => today + Days::new(((to + 7 - from - 1) % 7 + 1).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::dates::parse;
+
+    #[test]
+    fn parse_relative_dates() {
+        // A Wednesday
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let parse = |input| parse(input, today).unwrap().to_string();
+
+        assert_eq!(parse("today"), "2026-10-14");
+        assert_eq!(parse("Yesterday"), "2026-10-13");
+        assert_eq!(parse("tomorrow"), "2026-10-15");
+        assert_eq!(parse("2026-01-31"), "2026-01-31");
+        assert_eq!(parse("+3d"), "2026-10-17");
+        assert_eq!(parse("-2"), "2026-10-12");
+        assert_eq!(parse("-1w"), "2026-10-07");
+        assert_eq!(parse("+1m"), "2026-11-14");
+        assert_eq!(parse("-1y"), "2025-10-14");
+        assert_eq!(parse("last friday"), "2026-10-09");
+        assert_eq!(parse("next monday"), "2026-10-19");
+        assert_eq!(parse("last wed"), "2026-10-07");
+        assert_eq!(parse("next wednesday"), "2026-10-21");
+        assert_eq!(parse("wednesday"), "2026-10-14");
+        assert_eq!(parse("fri"), "2026-10-16");
+        assert_eq!(parse("next month"), "2026-11-14");
+        assert_eq!(parse("last week"), "2026-10-07");
+    }
+
+    #[test]
+    fn reject_invalid_dates() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        for input in [
+            "",
+            "someday",
+            "+3x",
+            "2026-02-30",
+            "last",
+            "next fortnight",
+            "in 3 days",
+        ] {
+            assert!(parse(input, today).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Write;
+
+/// Unchanged lines shown around each change
+pub const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff of two texts, like `diff -u`. Empty when they are the same.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    // Position in both texts before each op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for (op, _) in &ops {
+        positions.push((i, j));
+        match op {
+            Op::Equal => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    // Changes with their context, merged when they touch
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (k, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+    {
+        let (start, end) = (k.saturating_sub(CONTEXT), (k + CONTEXT + 1).min(ops.len()));
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+    for (start, end) in hunks {
+        let ops = &ops[start..end];
+        let count = |skip: Op| ops.iter().filter(|(op, _)| *op != skip).count();
+        let (old_count, new_count) = (count(Op::Insert), count(Op::Delete));
+        let (i, j) = positions[start];
+        let line = |pos: usize, count: usize| if count == 0 { pos } else { pos + 1 };
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_count} +{},{new_count} @@",
+            line(i, old_count),
+            line(j, new_count)
+        );
+        for (op, text) in ops {
+            let sign = match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            };
+            out.push(sign);
+            out.push_str(text);
+            if !text.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Shortest edit from `old` to `new` through their longest common
+/// subsequence, after skipping the common start and end
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the LCS length of a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = match a[i] == b[j] {
+                true => lcs[(i + 1) * width + j + 1] + 1,
+                false => lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]),
+            };
+        }
+    }
+
+    let mut ops = old[..prefix]
+        .iter()
+        .map(|line| (Op::Equal, *line))
+        .collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((Op::Equal, a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if j == b.len() || i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]
+        {
+            ops.push((Op::Delete, a[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| (Op::Equal, *line)),
+    );
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::unified;
+
+    #[test]
+    fn unified_diffs() {
+        assert_eq!(unified("a", "b", "same\n", "same\n"), "");
+
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13";
+        assert_eq!(
+            unified("a/n.md", "b/n.md", old, new),
+            "--- a/n.md\n+++ b/n.md\n\
+             @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+             @@ -10,3 +10,4 @@\n 10\n 11\n 12\n+13\n\\ No newline at end of file\n"
+        );
+
+        assert_eq!(
+            unified("a", "b", "", "new\n"),
+            "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+new\n"
+        );
+    }
+}
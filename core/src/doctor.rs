@@ -0,0 +1,182 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{editor::line_of, links::Resolved, vault::Vault};
+
+/// A link that leads nowhere
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenLink {
+    /// Vault-relative path of the linking note
+    pub from: PathBuf,
+    /// 1-based line of the link
+    pub line: usize,
+    /// The link as written
+    pub link: String,
+}
+
+/// Problems with the links of a vault. Paths are vault-relative.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LinkReport {
+    /// Links and markdown links to notes that don't exist
+    pub unresolved: Vec<BrokenLink>,
+    /// `![[embeds]]` of files that don't exist
+    pub missing_embeds: Vec<BrokenLink>,
+    /// Notes no other note links to
+    pub orphans: Vec<PathBuf>,
+    /// Notes without a working link to another note
+    pub dead_ends: Vec<PathBuf>,
+    /// Attachments no note links to
+    pub unused_attachments: Vec<PathBuf>,
+}
+
+impl LinkReport {
+    /// Resolves every link of the vault, reading only the notes with broken
+    /// links for their lines
+    pub fn new(vault: &Vault) -> Result<Self> {
+        let mut report = Self::default();
+        let mut linked = HashSet::new();
+
+        for note in &vault.notes {
+            let mut broken = vec![];
+            let mut dead_end = true;
+            for link in vault.resolve_links(&note.path) {
+                match link.target {
+                    Resolved::Unresolved => broken.push(link.link),
+                    Resolved::Note(path) if path != note.path => {
+                        dead_end = false;
+                        linked.insert(path);
+                    }
+                    Resolved::Attachment(path) => {
+                        linked.insert(path);
+                    }
+                    Resolved::Note(_) => {}
+                }
+            }
+            if dead_end {
+                report.dead_ends.push(note.path.clone());
+            }
+            if broken.is_empty() {
+                continue;
+            }
+
+            let content = read(&vault.root, &note.path)?;
+            for link in broken {
+                let Some(text) = content.get(link.range.clone()) else {
+                    continue;
+                };
+                let broken = BrokenLink {
+                    from: note.path.clone(),
+                    line: line_of(&content, link.range.start),
+                    link: text.to_string(),
+                };
+                match link.embed {
+                    true => report.missing_embeds.push(broken),
+                    false => report.unresolved.push(broken),
+                }
+            }
+        }
+
+        report.orphans = unlinked(vault.notes.iter().map(|note| &note.path), &linked);
+        report.unused_attachments = unlinked(
+            vault.attachments.iter().map(|attachment| &attachment.path),
+            &linked,
+        );
+        Ok(report)
+    }
+
+    /// Whether there are links or embeds that lead nowhere
+    pub fn has_broken_links(&self) -> bool {
+        !self.unresolved.is_empty() || !self.missing_embeds.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.has_broken_links()
+            && self.orphans.is_empty()
+            && self.dead_ends.is_empty()
+            && self.unused_attachments.is_empty()
+    }
+}
+
+fn unlinked<'a>(
+    paths: impl Iterator<Item = &'a PathBuf>,
+    linked: &HashSet<PathBuf>,
+) -> Vec<PathBuf> {
+    paths
+        .filter(|path| !linked.contains(*path))
+        .cloned()
+        .collect()
+}
+
+fn read(root: &Path, path: &Path) -> Result<String> {
+    let path = root.join(path);
+    fs::read_to_string(&path).with_context(|| format!("Could not read note {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::{
+        doctor::{BrokenLink, LinkReport},
+        vault::Vault,
+    };
+
+    #[test]
+    fn report_link_problems() {
+        let root = env::temp_dir().join("obsidian-rust-cli-doctor");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(root.join("Home.md"), "[[Hub]] [[Hub#Missing heading]]").unwrap();
+        fs::write(
+            root.join("Hub.md"),
+            "# Hub\n[[Home]] [[#Hub]]\n[[Gone]] ![[used.png]]\n![[lost.png]]",
+        )
+        .unwrap();
+        fs::write(root.join("Lonely.md"), "[[Lonely]] [text](nowhere.md)").unwrap();
+        fs::write(root.join("Leaf.md"), "no links").unwrap();
+        fs::write(root.join("assets/used.png"), "").unwrap();
+        fs::write(root.join("assets/unused.pdf"), "").unwrap();
+
+        let report = LinkReport::new(&Vault::load(&root, &[]).unwrap()).unwrap();
+        let broken = |from: &str, line, link: &str| BrokenLink {
+            from: PathBuf::from(from),
+            line,
+            link: link.to_string(),
+        };
+        assert_eq!(
+            report.unresolved,
+            vec![
+                broken("Hub.md", 3, "[[Gone]]"),
+                broken("Lonely.md", 1, "[text](nowhere.md)")
+            ]
+        );
+        assert_eq!(
+            report.missing_embeds,
+            vec![broken("Hub.md", 4, "![[lost.png]]")]
+        );
+        assert_eq!(
+            report.orphans,
+            vec![PathBuf::from("Leaf.md"), PathBuf::from("Lonely.md")]
+        );
+        // Self-links and broken links don't lead anywhere else
+        assert_eq!(
+            report.dead_ends,
+            vec![PathBuf::from("Leaf.md"), PathBuf::from("Lonely.md")]
+        );
+        assert_eq!(
+            report.unused_attachments,
+            vec![PathBuf::from("assets/unused.pdf")]
+        );
+        assert!(report.has_broken_links());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["missing_embeds"][0]["from"], "Hub.md");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+
+use crate::uri::Uri;
+
+pub const EDITOR_VARS: [&str; 2] = ["VISUAL", "EDITOR"];
+
+/// How notes are opened, the `open_with` config key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OpenWith {
+    /// The Obsidian app, through an `obsidian://` URI
+    #[default]
+    Obsidian,
+    /// `$VISUAL` or `$EDITOR`
+    Editor,
+    /// Any other command, run with the note's path
+    Command(String),
+}
+
+impl OpenWith {
+    /// Opens a note at a `Heading` or `^block` subpath, which editors get as
+    /// its `line` when they are known to take one
+    pub fn open(
+        &self,
+        vault: &Path,
+        path: &Path,
+        subpath: Option<&str>,
+        line: Option<usize>,
+    ) -> Result<()> {
+        match self {
+            OpenWith::Obsidian => {
+                let rel = path.strip_prefix(vault).unwrap_or(path);
+                Uri::open(vault, rel, subpath).launch()
+            }
+            _ => run(&self.command()?, path, line),
+        }
+    }
+
+    /// The editor command, `$VISUAL` or `$EDITOR` unless a command is set
+    pub fn command(&self) -> Result<String> {
+        match self {
+            OpenWith::Command(command) => Ok(command.clone()),
+            _ => EDITOR_VARS
+                .iter()
+                .find_map(|var| env::var(var).ok().filter(|val| !val.trim().is_empty()))
+                .context("No editor configured, set $VISUAL or $EDITOR"),
+        }
+    }
+}
+
+impl FromStr for OpenWith {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "" => Err(anyhow::Error::msg(
+                "Invalid open_with, expected obsidian, editor or a command",
+            )),
+            "obsidian" => Ok(OpenWith::Obsidian),
+            "editor" => Ok(OpenWith::Editor),
+            command => Ok(OpenWith::Command(command.to_string())),
+        }
+    }
+}
+
+impl Display for OpenWith {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenWith::Obsidian => write!(f, "obsidian"),
+            OpenWith::Editor => write!(f, "editor"),
+            OpenWith::Command(command) => write!(f, "{command}"),
+        }
+    }
+}
+
+/// Lets the user edit `text` in a temp file named `name` and returns the
+/// result. The file is removed afterwards.
+pub fn edit_text(command: &str, name: &str, text: &str) -> Result<String> {
+    let dir = env::temp_dir().join(format!("obsidian-rust-cli-edit-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    let result = fs::write(&path, text)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| run(command, &path, None))
+        .and_then(|_| {
+            fs::read_to_string(&path).with_context(|| format!("Could not read {}", path.display()))
+        });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Runs `command` with `path`, waiting for it to exit
+fn run(command: &str, path: &Path, line: Option<usize>) -> Result<()> {
+    let mut words = command.split_whitespace();
+    let program = words.next().context("Empty editor command")?;
+    let status = Command::new(program)
+        .args(words)
+        .args(jump_args(program, path, line))
+        .status()
+        .with_context(|| format!("Could not run editor `{command}`"))?;
+    if !status.success() {
+        return Err(anyhow::Error::msg(format!(
+            "Editor `{command}` exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+/// The path argument(s), with `line` in the form the editor understands
+fn jump_args(program: &str, path: &Path, line: Option<usize>) -> Vec<PathBuf> {
+    let name = Path::new(program)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let Some(line) = line else {
+        return vec![path.to_path_buf()];
+    };
+    let with_line = || PathBuf::from(format!("{}:{line}", path.display()));
+    match name.as_ref() {
+        "vi" | "vim" | "nvim" | "gvim" | "nano" | "emacs" | "emacsclient" | "kak" | "micro"
+        | "mg" | "joe" | "ne" => vec![PathBuf::from(format!("+{line}")), path.to_path_buf()],
+        "code" | "code-insiders" | "codium" | "cursor" => vec!["-g".into(), with_line()],
+        "hx" | "helix" | "subl" | "zed" | "gedit" => vec![with_line()],
+        _ => vec![path.to_path_buf()],
+    }
+}
+
+/// 1-based line number of a byte offset
+pub fn line_of(text: &str, pos: usize) -> usize {
+    text[..pos].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::editor::{OpenWith, jump_args, line_of};
+
+    #[test]
+    fn editor_line_args() {
+        let path = Path::new("notes/a.md");
+        assert_eq!(
+            jump_args("/usr/bin/nvim", path, Some(3)),
+            vec![PathBuf::from("+3"), PathBuf::from("notes/a.md")]
+        );
+        assert_eq!(
+            jump_args("code", path, Some(3)),
+            vec![PathBuf::from("-g"), PathBuf::from("notes/a.md:3")]
+        );
+        assert_eq!(
+            jump_args("hx", path, Some(3)),
+            vec![PathBuf::from("notes/a.md:3")]
+        );
+        assert_eq!(
+            jump_args("ed", path, Some(3)),
+            vec![PathBuf::from("notes/a.md")]
+        );
+        assert_eq!(
+            jump_args("vim", path, None),
+            vec![PathBuf::from("notes/a.md")]
+        );
+        assert_eq!(line_of("a\nb\nc", 4), 3);
+    }
+
+    #[test]
+    fn parse_open_with() {
+        assert_eq!("obsidian".parse::<OpenWith>().unwrap(), OpenWith::Obsidian);
+        assert_eq!("editor".parse::<OpenWith>().unwrap(), OpenWith::Editor);
+        let command = "code --wait".parse::<OpenWith>().unwrap();
+        assert_eq!(command, OpenWith::Command("code --wait".into()));
+        assert_eq!(command.command().unwrap(), "code --wait");
+        assert!(" ".parse::<OpenWith>().is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use std::fmt::{self, Display};
+
+const DELIMITER: &str = "---";
+
+/// A property value, in the types Obsidian's properties editor knows.
+/// Dates are kept as text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    Empty,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    List(Vec<String>),
+}
+
+impl Property {
+    /// Parses an inline YAML value: `true`, `3`, `"quoted"`, `[a, b]` or text
+    pub fn parse(val: &str) -> Self {
+        let val = strip_comment(val.trim());
+        if let Some(items) = val.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            return Property::List(
+                split_items(items)
+                    .into_iter()
+                    .map(unquote)
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+            );
+        }
+        match val {
+            "" | "~" | "null" => Property::Empty,
+            "true" => Property::Bool(true),
+            "false" => Property::Bool(false),
+            _ if val.starts_with(['"', '\'']) => Property::Text(unquote(val)),
+            _ => match val.parse::<f64>() {
+                Result::Ok(num) if num.is_finite() => Property::Number(num),
+                _ => Property::Text(val.to_string()),
+            },
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Property::Text(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Lists as they are, any other value but `Empty` as a single item
+    pub fn to_list(&self) -> Vec<String> {
+        match self {
+            Property::Empty => vec![],
+            Property::List(items) => items.clone(),
+            val => vec![val.to_string()],
+        }
+    }
+
+    /// `key: value`, with list items on their own lines like Obsidian writes them
+    fn to_yaml(&self, key: &str) -> String {
+        let key = quote(key);
+        match self {
+            Property::Empty => format!("{key}:\n"),
+            Property::Bool(val) => format!("{key}: {val}\n"),
+            Property::Number(val) => format!("{key}: {val}\n"),
+            Property::Text(val) => format!("{key}: {}\n", quote(val)),
+            Property::List(items) if items.is_empty() => format!("{key}: []\n"),
+            Property::List(items) => items.iter().fold(format!("{key}:\n"), |yaml, item| {
+                format!("{yaml}  - {}\n", quote(item))
+            }),
+        }
+    }
+}
+
+impl Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Property::Empty => Ok(()),
+            Property::Bool(val) => write!(f, "{val}"),
+            Property::Number(val) => write!(f, "{val}"),
+            Property::Text(val) => write!(f, "{val}"),
+            Property::List(items) => write!(f, "{}", items.join(", ")),
+        }
+    }
+}
+
+impl From<&str> for Property {
+    fn from(val: &str) -> Self {
+        Property::Text(val.to_string())
+    }
+}
+
+/// The YAML frontmatter of a note, kept as the original text of each
+/// top-level key so that editing one property leaves the others untouched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frontmatter {
+    entries: Vec<Entry>,
+    /// The block's line ending, `\r\n` when the note was written so
+    crlf: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    /// Empty for comments and blank lines before the first key
+    key: String,
+    text: String,
+}
+
+impl Frontmatter {
+    /// Splits `src` into its frontmatter and the rest. Notes without a
+    /// `---` block get an empty frontmatter and are returned whole.
+    pub fn parse(src: &str) -> (Self, &str) {
+        let Some((yaml, rest)) = split(src) else {
+            return (Self::default(), src);
+        };
+        let crlf = src.starts_with("---\r\n");
+        let mut entries: Vec<Entry> = vec![];
+        for line in yaml.split_inclusive('\n') {
+            let top_level = !line.starts_with([' ', '\t', '-', '#']);
+            match key_of(line).filter(|_| top_level) {
+                Some(key) => entries.push(Entry {
+                    key,
+                    text: line.to_string(),
+                }),
+                None => match entries.last_mut() {
+                    Some(entry) => entry.text.push_str(line),
+                    None => entries.push(Entry {
+                        key: String::new(),
+                        text: line.to_string(),
+                    }),
+                },
+            }
+        }
+        (Self { entries, crlf }, rest)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Property> {
+        self.entry(key).map(Entry::value)
+    }
+
+    /// Every property in file order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Property)> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.key.is_empty())
+            .map(|entry| (entry.key.as_str(), entry.value()))
+    }
+
+    /// Replaces the value in place, or appends a new key at the end
+    pub fn set(&mut self, key: &str, val: Property) {
+        let text = match self.crlf {
+            true => val.to_yaml(key).replace('\n', "\r\n"),
+            false => val.to_yaml(key),
+        };
+        let newline = self.newline();
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => entry.text = text,
+            None => {
+                if let Some(last) = self.entries.last_mut()
+                    && !last.text.ends_with('\n')
+                {
+                    last.text.push_str(newline);
+                }
+                self.entries.push(Entry {
+                    key: key.to_string(),
+                    text,
+                });
+            }
+        }
+    }
+
+    /// Returns whether the key was there
+    pub fn remove(&mut self, key: &str) -> bool {
+        let len = self.entries.len();
+        self.entries
+            .retain(|entry| key.is_empty() || entry.key != key);
+        self.entries.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| entry.text.trim().is_empty())
+    }
+
+    fn newline(&self) -> &'static str {
+        match self.crlf {
+            true => "\r\n",
+            false => "\n",
+        }
+    }
+
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| !key.is_empty() && entry.key == key)
+    }
+}
+
+/// The `---` block, or nothing when there are no properties left
+impl Display for Frontmatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let newline = self.newline();
+        write!(f, "{DELIMITER}{newline}")?;
+        for entry in &self.entries {
+            write!(f, "{}", entry.text)?;
+        }
+        write!(f, "{DELIMITER}{newline}")
+    }
+}
+
+impl Entry {
+    fn value(&self) -> Property {
+        let (first, rest) = self.text.split_once('\n').unwrap_or((&self.text, ""));
+        let inline = key_end(first).map_or("", |end| &first[end + 1..]).trim();
+        let lines = rest.lines().filter(|line| !line.trim().is_empty());
+
+        if let Some(style) = inline.chars().next().filter(|c| matches!(c, '|' | '>')) {
+            let sep = if style == '|' { "\n" } else { " " };
+            return Property::Text(lines.map(str::trim).collect::<Vec<_>>().join(sep));
+        }
+        if !inline.is_empty() {
+            return Property::parse(inline);
+        }
+        let lines = lines.map(str::trim).collect::<Vec<_>>();
+        if lines.is_empty() {
+            Property::Empty
+        } else if lines.iter().all(|line| line.starts_with('-')) {
+            Property::List(lines.iter().map(|line| unquote(&line[1..])).collect())
+        } else {
+            Property::Text(lines.join("\n"))
+        }
+    }
+}
+
+/// Splits a leading `---` block off `src` into its YAML and the rest
+fn split(src: &str) -> Option<(&str, &str)> {
+    let yaml = src
+        .strip_prefix("---\n")
+        .or_else(|| src.strip_prefix("---\r\n"))?;
+    let mut pos = 0;
+    for line in yaml.split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            return Some((&yaml[..pos], &yaml[pos + line.len()..]));
+        }
+        pos += line.len();
+    }
+    None
+}
+
+fn key_of(line: &str) -> Option<String> {
+    key_end(line).map(|end| unquote(&line[..end]))
+}
+
+/// Byte offset of the `:` after a top-level key, which may be quoted
+fn key_end(line: &str) -> Option<usize> {
+    match line.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = line[1..].find(quote)? + 2;
+            line[end..].starts_with(':').then_some(end)
+        }
+        _ => line.find(':'),
+    }
+}
+
+fn strip_comment(val: &str) -> &str {
+    let Some(quote) = val.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+        return val.split_once(" #").map_or(val, |(val, _)| val.trim_end());
+    };
+    let mut escaped = false;
+    for (i, c) in val.char_indices().skip(1) {
+        match c {
+            '\\' if quote == '"' => escaped = !escaped,
+            c if c == quote && !escaped => return &val[..=i],
+            _ => escaped = false,
+        }
+    }
+    val
+}
+
+/// Splits the inside of `[a, "b, c"]` on the commas outside of quotes
+fn split_items(items: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut start, mut quote) = (0, None);
+    let mut chars = items.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            // `\"` in double quotes, `''` in single quotes
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some('\''), '\'') if chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') if items[start..i].trim().is_empty() => quote = Some(c),
+            (None, ',') => {
+                parts.push(&items[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&items[start..]);
+    parts
+}
+
+fn unquote(val: &str) -> String {
+    let val = val.trim();
+    if let Some(inner) = val.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        inner.replace("\\\"", "\"").replace("\\\\", "\\")
+    } else if let Some(inner) = val.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        inner.replace("''", "'")
+    } else {
+        val.to_string()
+    }
+}
+
+/// Double-quotes text that would not read back as the same string
+fn quote(val: &str) -> String {
+    let plain = !val.is_empty()
+        && val.trim() == val
+        && !val.starts_with(|c| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !val.contains(": ")
+        && !val.contains(" #")
+        && Property::parse(val) == Property::Text(val.to_string());
+    if plain {
+        val.to_string()
+    } else {
+        format!("\"{}\"", val.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontmatter::{Frontmatter, Property};
+
+    const NOTE: &str = "\
+---
+# kept
+title: \"Plan: Q3\"   # inline comment
+tags:
+    - work
+    - 'q3'
+aliases: [plan, roadmap]
+created: 2025-08-15
+done: false
+points: 3
+summary: |
+  line one
+  line two
+---
+# Plan
+";
+
+    #[test]
+    fn read_properties() {
+        let (props, rest) = Frontmatter::parse(NOTE);
+        assert_eq!(rest, "# Plan\n");
+        assert_eq!(props.get("title"), Some(Property::from("Plan: Q3")));
+        assert_eq!(
+            props.get("tags"),
+            Some(Property::List(vec!["work".into(), "q3".into()]))
+        );
+        assert_eq!(
+            props.get("aliases"),
+            Some(Property::List(vec!["plan".into(), "roadmap".into()]))
+        );
+        assert_eq!(props.get("created"), Some(Property::from("2025-08-15")));
+        assert_eq!(props.get("done"), Some(Property::Bool(false)));
+        assert_eq!(props.get("points"), Some(Property::Number(3.0)));
+        assert_eq!(
+            props.get("summary"),
+            Some(Property::from("line one\nline two"))
+        );
+        assert_eq!(props.get("missing"), None);
+        assert_eq!(props.iter().count(), 7);
+
+        assert_eq!(
+            Property::parse(r#"["a, b", c, 'it''s, ok', "say \"hi, you\""]"#),
+            Property::List(vec![
+                "a, b".into(),
+                "c".into(),
+                "it's, ok".into(),
+                "say \"hi, you\"".into()
+            ])
+        );
+    }
+
+    #[test]
+    fn edits_keep_other_keys() {
+        let (mut props, rest) = Frontmatter::parse(NOTE);
+        assert_eq!(format!("{props}{rest}"), NOTE);
+
+        props.set("done", Property::Bool(true));
+        props.set("status", Property::from("in progress"));
+        props.set("owner", Property::from("#team"));
+        props.set("related", Property::List(vec!["[[Roadmap]]".into()]));
+        assert!(props.remove("aliases"));
+        assert!(!props.remove("aliases"));
+
+        let expected = NOTE
+            .replace("done: false", "done: true")
+            .replace("aliases: [plan, roadmap]\n", "")
+            .replace(
+                "  line two\n",
+                "  line two\nstatus: in progress\nowner: \"#team\"\nrelated:\n  - \"[[Roadmap]]\"\n",
+            );
+        assert_eq!(format!("{props}{rest}"), expected);
+
+        let (reparsed, _) = Frontmatter::parse(&expected);
+        assert_eq!(reparsed.get("owner"), Some(Property::from("#team")));
+        assert_eq!(
+            reparsed.get("related"),
+            Some(Property::List(vec!["[[Roadmap]]".into()]))
+        );
+    }
+
+    #[test]
+    fn edits_keep_crlf() {
+        let src = "---\r\ntags: [a]\r\ndone: false\r\n---\r\nbody\r\n";
+        let (mut props, rest) = Frontmatter::parse(src);
+        assert_eq!(format!("{props}{rest}"), src);
+
+        props.set("done", Property::Bool(true));
+        props.set("owner", Property::List(vec!["Ann".into()]));
+        assert_eq!(
+            format!("{props}{rest}"),
+            "---\r\ntags: [a]\r\ndone: true\r\nowner:\r\n  - Ann\r\n---\r\nbody\r\n"
+        );
+        assert_eq!(props.get("owner"), Some(Property::List(vec!["Ann".into()])));
+    }
+
+    #[test]
+    fn notes_without_frontmatter() {
+        let (mut props, rest) = Frontmatter::parse("# Title\n---\n");
+        assert_eq!(rest, "# Title\n---\n");
+        assert_eq!(props.to_string(), "");
+
+        props.set("tags", Property::List(vec![]));
+        assert_eq!(props.to_string(), "---\ntags: []\n---\n");
+        props.remove("tags");
+        assert_eq!(props.to_string(), "");
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atomic,
+    vault::{NoteMeta, Vault, content_hash},
+};
+
+/// Bumped whenever `NoteMeta` or the way notes are parsed changes, so
+/// indexes saved by older versions are rebuilt
+pub const PARSER_VERSION: u32 = 2;
+pub const INDEX_DIR: &str = "index";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    root: PathBuf,
+    notes: Vec<NoteMeta>,
+}
+
+/// The parsed notes of a vault, saved between runs so only changed files
+/// are parsed again
+#[derive(Debug, Clone)]
+pub struct IndexCache {
+    pub path: PathBuf,
+}
+
+impl IndexCache {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// One file per vault in `dir`, named after the vault and its path
+    pub fn for_vault(dir: &Path, vault: &Path) -> Self {
+        let vault = canonical(vault);
+        let name = vault.file_name().unwrap_or_default().to_string_lossy();
+        let key = content_hash(vault.to_string_lossy().as_bytes());
+        Self::new(dir.join(format!("{name}-{key:016x}.json")))
+    }
+
+    /// The saved notes, or `None` when nothing is saved for `root` by this
+    /// version. A damaged file is ignored, it is rebuilt anyway.
+    pub fn read(&self, root: &Path) -> Result<Option<Vault>> {
+        let json = match fs::read_to_string(&self.path) {
+            Result::Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Could not read index {}", self.path.display()));
+            }
+        };
+        let Result::Ok(file) = serde_json::from_str::<IndexFile>(&json) else {
+            return Ok(None);
+        };
+        if file.version != PARSER_VERSION || file.root != canonical(root) {
+            return Ok(None);
+        }
+        Ok(Some(Vault::new(
+            root.to_path_buf(),
+            file.notes,
+            vec![],
+            vec![],
+        )))
+    }
+
+    pub fn write(&self, vault: &Vault) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+        let file = IndexFile {
+            version: PARSER_VERSION,
+            root: canonical(&vault.root),
+            notes: vault.notes.clone(),
+        };
+        atomic::write(&self.path, &serde_json::to_vec(&file)?, false)
+    }
+
+    /// Returns whether there was an index to remove
+    pub fn clear(&self) -> Result<bool> {
+        match fs::remove_file(&self.path) {
+            Result::Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => {
+                Err(err).with_context(|| format!("Could not remove {}", self.path.display()))
+            }
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::{
+        index::{IndexCache, PARSER_VERSION},
+        vault::{Refresh, Vault},
+    };
+
+    #[test]
+    fn refresh_from_cache() {
+        let root = env::temp_dir().join("obsidian-rust-cli-index");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join("notes/a.md"), "#one").unwrap();
+        fs::write(root.join("notes/b.md"), "[[a]]").unwrap();
+        let cache = IndexCache::for_vault(&root.join("cache"), &root);
+
+        assert!(cache.read(&root).unwrap().is_none());
+        let (vault, refresh) = Vault::refresh(&root, &[], &Vault::default()).unwrap();
+        assert_eq!(refresh.parsed, 2);
+        cache.write(&vault).unwrap();
+
+        // Touched without changes, removed and added
+        let cached = cache.read(&root).unwrap().unwrap();
+        assert_eq!(cached.notes, vault.notes);
+        let mut a = cached.notes[0].clone();
+        a.mtime = SystemTime::UNIX_EPOCH;
+        let cached = Vault::new(
+            root.clone(),
+            vec![a, cached.notes[1].clone()],
+            vec![],
+            vec![],
+        );
+        fs::remove_file(root.join("notes/b.md")).unwrap();
+        fs::write(root.join("notes/c.md"), "#two").unwrap();
+        let (vault, refresh) = Vault::refresh(&root, &[], &cached).unwrap();
+        assert_eq!(
+            refresh,
+            Refresh {
+                reused: 0,
+                touched: 1,
+                parsed: 1,
+                removed: 1,
+                skipped: 0
+            }
+        );
+        assert_eq!(
+            vault.notes[0].mtime,
+            fs::metadata(root.join("notes/a.md"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        );
+        assert_eq!(vault.notes[1].tags, vec!["two"]);
+
+        // A touched note is saved with its new mtime, so the next refresh
+        // doesn't read it again
+        cache.write(&vault).unwrap();
+        File::options()
+            .write(true)
+            .open(root.join("notes/c.md"))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(60))
+            .unwrap();
+        let (vault, refresh) =
+            Vault::refresh(&root, &[], &cache.read(&root).unwrap().unwrap()).unwrap();
+        assert_eq!((refresh.reused, refresh.touched), (1, 1));
+        assert!(refresh.changed());
+        cache.write(&vault).unwrap();
+        let (_, refresh) =
+            Vault::refresh(&root, &[], &cache.read(&root).unwrap().unwrap()).unwrap();
+        assert_eq!((refresh.reused, refresh.touched), (2, 0));
+        assert!(!refresh.changed());
+
+        // Another parser version or vault is not used
+        let json = fs::read_to_string(&cache.path).unwrap().replacen(
+            &format!("\"version\":{PARSER_VERSION}"),
+            "\"version\":0",
+            1,
+        );
+        fs::write(&cache.path, json).unwrap();
+        assert!(cache.read(&root).unwrap().is_none());
+        cache.write(&vault).unwrap();
+        assert!(cache.read(&PathBuf::from("/elsewhere")).unwrap().is_none());
+        fs::write(&cache.path, "{").unwrap();
+        assert!(cache.read(&root).unwrap().is_none());
+
+        assert!(cache.clear().unwrap());
+        assert!(!cache.clear().unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
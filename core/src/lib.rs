@@ -1,4 +1,20 @@
+pub mod atomic;
 pub mod config;
+pub mod config_file;
+pub mod dates;
+pub mod diff;
+pub mod doctor;
+pub mod editor;
+pub mod frontmatter;
+pub mod index;
+pub mod links;
+pub mod markdown;
+pub mod moment;
+pub mod naming;
 pub mod note;
+pub mod obsidian;
+pub mod periodic;
+pub mod rename;
 pub mod template;
+pub mod uri;
 pub mod vault;
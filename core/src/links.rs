@@ -0,0 +1,352 @@
+use std::{
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    editor::line_of,
+    markdown::{self, Link, LinkKind},
+    vault::{Vault, is_note},
+};
+
+/// Longest context shown around a backlink, in characters
+pub const MAX_CONTEXT: usize = 120;
+
+/// What a link points to in the vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolved {
+    /// Vault-relative path of a note
+    Note(PathBuf),
+    /// Vault-relative path of any other file
+    Attachment(PathBuf),
+    Unresolved,
+}
+
+impl Resolved {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Resolved::Note(path) | Resolved::Attachment(path) => Some(path),
+            Resolved::Unresolved => None,
+        }
+    }
+}
+
+/// A link of a note with where it leads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedLink {
+    pub link: Link,
+    pub target: Resolved,
+}
+
+/// A link to a note from another note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlink {
+    /// Vault-relative path of the linking note
+    pub from: PathBuf,
+    pub link: Link,
+    /// 1-based line of the link
+    pub line: usize,
+    /// The link's line, shortened around the link when it is long
+    pub context: String,
+}
+
+impl Vault {
+    /// Links to the note or file at `to` from every other note, reading the
+    /// linking notes for the context of each link
+    pub fn backlinks(&self, to: &Path) -> Result<Vec<Backlink>> {
+        let mut backlinks = vec![];
+        for note in self.notes.iter().filter(|note| note.path != to) {
+            let links = note
+                .links
+                .iter()
+                .filter(|link| self.resolve(link, &note.path).path() == Some(to))
+                .collect::<Vec<_>>();
+            if links.is_empty() {
+                continue;
+            }
+            let path = self.root.join(&note.path);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read note {}", path.display()))?;
+            for link in links {
+                // The index may be older than the file for a moment
+                let Some(context) = context(&content, &link.range) else {
+                    continue;
+                };
+                backlinks.push(Backlink {
+                    from: note.path.clone(),
+                    line: line_of(&content, link.range.start),
+                    context,
+                    link: link.clone(),
+                });
+            }
+        }
+        Ok(backlinks)
+    }
+
+    /// Resolves every link of an indexed note
+    pub fn resolve_links(&self, from: &Path) -> Vec<ResolvedLink> {
+        let Some(note) = self.note(from) else {
+            return vec![];
+        };
+        note.links
+            .iter()
+            .map(|link| ResolvedLink {
+                target: self.resolve(link, from),
+                link: link.clone(),
+            })
+            .collect()
+    }
+
+    /// Resolves a link in the note at `from` like Obsidian does:
+    ///
+    /// - an empty target is the note itself, `[[#Heading]]`
+    /// - markdown links and `./`, `../` targets are relative to the note's folder
+    /// - otherwise the target is a vault path, or the end of one, ignoring
+    ///   case, so `[[Note]]` and `[[b/Note]]` find `a/b/Note.md`
+    /// - without `.md` notes come first, then files with that exact name
+    /// - several matches prefer the note's own folder, then the shortest path
+    /// - failing that, a note with the target as its only alias
+    pub fn resolve(&self, link: &Link, from: &Path) -> Resolved {
+        let target = link.target.trim().replace('\\', "/");
+        if target.is_empty() {
+            return match self.note(from) {
+                Some(note) => Resolved::Note(note.path.clone()),
+                None => Resolved::Unresolved,
+            };
+        }
+        let folder = from.parent().unwrap_or(Path::new(""));
+
+        let relative = link.kind == LinkKind::Markdown || target.starts_with('.');
+        if relative {
+            if let Some(path) = normalize(&folder.join(&target))
+                && let Some(found) = self.exact(&path)
+            {
+                return found;
+            }
+            if target.starts_with('.') {
+                return Resolved::Unresolved;
+            }
+        }
+        let target = target.trim_start_matches('/');
+        if let Some(found) = self.exact(Path::new(target)) {
+            return found;
+        }
+        if let Some(found) = self.by_suffix(target, folder) {
+            return found;
+        }
+        match self.by_alias(target).as_slice() {
+            [note] if !target.contains('/') => Resolved::Note(note.path.clone()),
+            _ => Resolved::Unresolved,
+        }
+    }
+
+    /// The note or file at exactly this path, `.md` optional for notes
+    fn exact(&self, path: &Path) -> Option<Resolved> {
+        let with_md = PathBuf::from(format!("{}.md", path.display()));
+        if let Some(note) = self.note(&with_md).or_else(|| self.note(path)) {
+            return Some(Resolved::Note(note.path.clone()));
+        }
+        self.attachment(path)
+            .map(|attachment| Resolved::Attachment(attachment.path.clone()))
+    }
+
+    /// The best note or file whose path ends with `target`, ignoring case
+    fn by_suffix(&self, target: &str, folder: &Path) -> Option<Resolved> {
+        let target = target.to_lowercase();
+        let ends_with = |path: &Path, end: &str| {
+            let path = path.to_string_lossy().replace('\\', "/").to_lowercase();
+            path == end || path.ends_with(&format!("/{end}"))
+        };
+
+        let note_target = match is_note(Path::new(&target)) {
+            true => target.clone(),
+            false => format!("{target}.md"),
+        };
+        let title = note_target.rsplit('/').next().unwrap_or_default();
+        let notes = self
+            .by_title(title.strip_suffix(".md").unwrap_or(title))
+            .into_iter()
+            .map(|note| note.path.as_path())
+            .filter(|path| ends_with(path, &note_target))
+            .collect();
+        if let Some(path) = best(notes, folder) {
+            return Some(Resolved::Note(path.to_path_buf()));
+        }
+
+        let name = target.rsplit('/').next().unwrap_or_default();
+        let files = self
+            .attachments_by_name(name)
+            .into_iter()
+            .map(|attachment| attachment.path.as_path())
+            .filter(|path| ends_with(path, &target))
+            .collect();
+        best(files, folder).map(|path| Resolved::Attachment(path.to_path_buf()))
+    }
+}
+
+/// The trimmed line around `range`, cut to `MAX_CONTEXT` characters
+/// around the link
+fn context(content: &str, range: &Range<usize>) -> Option<String> {
+    content.get(range.clone())?;
+    let start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = markdown::line_end(content, range.start);
+    let line = content[start..end].trim();
+    if line.chars().count() <= MAX_CONTEXT {
+        return Some(line.to_string());
+    }
+
+    // Characters before the link in the line, and from there on
+    let before = content[start..range.start].trim_start().chars().count();
+    let from = before.saturating_sub(MAX_CONTEXT / 4);
+    let mut cut = line
+        .chars()
+        .skip(from)
+        .take(MAX_CONTEXT)
+        .collect::<String>();
+    if from > 0 {
+        cut.insert(0, '…');
+    }
+    if from + MAX_CONTEXT < line.chars().count() {
+        cut.push('…');
+    }
+    Some(cut)
+}
+
+/// Of several matches the one in `folder`, else the one closest to the root
+fn best<'a>(paths: Vec<&'a Path>, folder: &Path) -> Option<&'a Path> {
+    paths.into_iter().min_by_key(|path| {
+        (
+            path.parent() != Some(folder),
+            path.components().count(),
+            path.to_path_buf(),
+        )
+    })
+}
+
+/// `path` with `.` and `..` folded away, `None` when it leaves the vault
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(part) => out.push(part),
+            Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    use crate::{
+        links::{MAX_CONTEXT, Resolved},
+        vault::{Attachment, NoteMeta, Vault},
+    };
+
+    fn note(path: &str, content: &str) -> NoteMeta {
+        NoteMeta::parse(PathBuf::from(path), content, SystemTime::UNIX_EPOCH, 0)
+    }
+
+    fn attachment(path: &str) -> Attachment {
+        Attachment {
+            path: PathBuf::from(path),
+            mtime: SystemTime::UNIX_EPOCH,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn resolve_like_obsidian() {
+        let src = "[[Note]] [[a/note]] [[Deep]] [[y/Deep#Part|d]] ![[image.PNG]] [[My.Note]] \
+            [[ann]] [[#Heading]] [up](../Note.md) [here](Note.md) [[Missing]] [out](../../out.md) \
+            [[Doc.pdf]] [[./Deep]]";
+        let vault = Vault::new(
+            PathBuf::from("/vault"),
+            vec![
+                note("Note.md", ""),
+                note("a/Note.md", ""),
+                note("a/Source.md", src),
+                note("c/Deep.md", ""),
+                note("x/y/Deep.md", "[[Deep]]"),
+                note("My.Note.md", ""),
+                note("People/Ann Lee.md", "---\naliases: [Ann]\n---\n"),
+            ],
+            vec![attachment("assets/Image.png"), attachment("Doc.pdf")],
+            vec![],
+        );
+
+        let targets = vault
+            .resolve_links(Path::new("a/Source.md"))
+            .into_iter()
+            .map(|link| link.target)
+            .collect::<Vec<_>>();
+        let note = |path: &str| Resolved::Note(PathBuf::from(path));
+        assert_eq!(
+            targets,
+            vec![
+                note("Note.md"),
+                note("a/Note.md"),
+                note("c/Deep.md"),
+                note("x/y/Deep.md"),
+                Resolved::Attachment(PathBuf::from("assets/Image.png")),
+                note("My.Note.md"),
+                note("People/Ann Lee.md"),
+                note("a/Source.md"),
+                note("Note.md"),
+                note("a/Note.md"),
+                Resolved::Unresolved,
+                Resolved::Unresolved,
+                Resolved::Attachment(PathBuf::from("Doc.pdf")),
+                Resolved::Unresolved,
+            ]
+        );
+
+        // The note's own folder wins over a shorter path
+        let links = vault.resolve_links(Path::new("x/y/Deep.md"));
+        assert_eq!(links[0].target.path(), Some(Path::new("x/y/Deep.md")));
+        assert_eq!(links[0].link.range, 0..8);
+    }
+
+    #[test]
+    fn backlinks_with_context() {
+        let root = env::temp_dir().join("obsidian-rust-cli-backlinks");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("Target.md"), "# Top\nSelf [[Target]]").unwrap();
+        fs::write(
+            root.join("a/One.md"),
+            "---\ntags: x\n---\nIntro\n- See [[target#Top]] here\n[[Other]]",
+        )
+        .unwrap();
+        let long = format!("{} [[Target]] {}", "word ".repeat(40), "end ".repeat(40));
+        fs::write(root.join("a/Two.md"), &long).unwrap();
+
+        let vault = Vault::load(&root, &[]).unwrap();
+        let backlinks = vault.backlinks(Path::new("Target.md")).unwrap();
+        assert_eq!(backlinks.len(), 2);
+        assert_eq!(backlinks[0].from, PathBuf::from("a/One.md"));
+        assert_eq!(backlinks[0].line, 5);
+        assert_eq!(backlinks[0].context, "- See [[target#Top]] here");
+        assert_eq!(backlinks[0].link.subpath.as_deref(), Some("Top"));
+
+        let context = &backlinks[1].context;
+        assert!(context.starts_with('…') && context.ends_with('…'));
+        assert!(context.contains("[[Target]]"));
+        assert_eq!(context.chars().count(), MAX_CONTEXT + 2);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use std::ops::Range;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static HEADING_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(#{1,6})[ \t]+(.*?)[ \t#]*$").unwrap());
+static WIKILINK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(!)?\[\[([^\[\]|#^]*)([#^][^\[\]|]*)?(?:\|([^\[\]]*))?\]\]").unwrap()
+});
+static MDLINK_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(!)?\[([^\[\]]*)\]\(\s*(<[^>]*>|[^)\s]+)(?:\s+"[^"]*")?\s*\)"#).unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    /// Byte range of the heading's line, without the line break
+    pub range: Range<usize>,
+}
+
+/// A heading and everything under it up to the next heading of the same
+/// or a higher level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub heading: Heading,
+    /// From the line after the heading to the start of the next section
+    pub body: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkKind {
+    /// `[[target#heading|alias]]`
+    Wiki,
+    /// `[alias](target.md#heading)`
+    Markdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// The linked note as written, empty for links inside the same note
+    pub target: String,
+    /// `#Heading` or `#^block`, without the `#`
+    pub subpath: Option<String>,
+    pub alias: Option<String>,
+    /// `![[...]]`
+    pub embed: bool,
+    /// Byte range of the whole link in the source
+    pub range: Range<usize>,
+}
+
+/// ATX headings outside of code blocks
+pub fn headings(src: &str) -> Vec<Heading> {
+    lines(src)
+        .filter_map(|(range, line)| {
+            let caps = HEADING_REGEX.captures(line)?;
+            Some(Heading {
+                level: caps[1].len(),
+                text: caps[2].to_string(),
+                range,
+            })
+        })
+        .collect()
+}
+
+/// The first section under a heading with this text, `#`s and case ignored
+pub fn section(src: &str, heading: &str) -> Option<Section> {
+    let name = heading.trim().trim_start_matches('#').trim();
+    let headings = headings(src);
+    let index = headings
+        .iter()
+        .position(|h| h.text.eq_ignore_ascii_case(name))?;
+    let heading = headings[index].clone();
+    let start = line_end(src, heading.range.end);
+    let end = headings[index + 1..]
+        .iter()
+        .find(|next| next.level <= heading.level)
+        .map_or(src.len(), |next| next.range.start);
+    Some(Section {
+        heading,
+        body: start..end,
+    })
+}
+
+/// The line ending with the block id `^id`, without its line break
+pub fn block(src: &str, id: &str) -> Option<Range<usize>> {
+    let marker = format!("^{}", id.trim_start_matches('^'));
+    lines(src)
+        .find(|(_, line)| {
+            line.trim_end()
+                .strip_suffix(marker.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with([' ', '\t']))
+        })
+        .map(|(range, _)| range)
+}
+
+/// Offset of the start of the line after `pos`, or the end of `src`
+pub fn line_end(src: &str, pos: usize) -> usize {
+    src[pos..].find('\n').map_or(src.len(), |i| pos + i + 1)
+}
+
+/// Wikilinks and links to local files outside of code. URLs are skipped.
+pub fn links(src: &str) -> Vec<Link> {
+    let mut links = vec![];
+    for (range, line) in lines(src) {
+        let line = mask_code_spans(line);
+        for caps in WIKILINK_REGEX.captures_iter(&line) {
+            let whole = caps.get(0).unwrap();
+            links.push(Link {
+                kind: LinkKind::Wiki,
+                target: caps[2].trim().to_string(),
+                subpath: caps.get(3).map(|m| subpath(m.as_str())),
+                alias: caps.get(4).map(|m| m.as_str().to_string()),
+                embed: caps.get(1).is_some(),
+                range: range.start + whole.start()..range.start + whole.end(),
+            });
+        }
+        for caps in MDLINK_REGEX.captures_iter(&line) {
+            let whole = caps.get(0).unwrap();
+            let dest = caps[3].trim_start_matches('<').trim_end_matches('>');
+            if dest.contains("://") || dest.starts_with("mailto:") {
+                continue;
+            }
+            let dest = urlencoding::decode(dest).map_or(dest.to_string(), |d| d.into_owned());
+            let (target, sub) = match dest.split_once('#') {
+                Some((target, sub)) => (target.to_string(), Some(sub.to_string())),
+                None => (dest, None),
+            };
+            links.push(Link {
+                kind: LinkKind::Markdown,
+                target,
+                subpath: sub,
+                alias: Some(caps[2].to_string()).filter(|alias| !alias.is_empty()),
+                embed: caps.get(1).is_some(),
+                range: range.start + whole.start()..range.start + whole.end(),
+            });
+        }
+    }
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// Lines outside fenced code blocks with their byte ranges, line breaks excluded
+pub fn lines(src: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut fence: Option<&str> = None;
+    let mut pos = 0;
+    src.split_inclusive('\n').filter_map(move |raw| {
+        let start = pos;
+        pos += raw.len();
+        let line = raw.trim_end_matches(['\r', '\n']);
+        let trimmed = line.trim_start();
+        match fence {
+            Some(open) if trimmed.starts_with(open) => {
+                fence = None;
+                None
+            }
+            Some(_) => None,
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+                None
+            }
+            None => Some((start..start + line.len(), line)),
+        }
+    })
+}
+
+fn subpath(val: &str) -> String {
+    val.trim_start_matches('#').trim().to_string()
+}
+
+/// Blanks out `inline code` so links in it are not matched, keeping offsets
+pub(crate) fn mask_code_spans(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    for c in line.chars() {
+        if c == '`' {
+            in_code = !in_code;
+            out.push(c);
+        } else if in_code {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{Link, LinkKind, block, headings, links, section};
+
+    const SRC: &str = "\
+# Title
+Intro [[Other note#Part|see]] and ![[image.png]]
+```
+# not a heading [[nope]]
+```
+## Log ##
+- [md](Folder/My%20Note.md#Log) `[[code]]` [web](https://example.com) [[#^block]]
+";
+
+    #[test]
+    fn parse_headings() {
+        let headings = headings(SRC);
+        assert_eq!(
+            headings
+                .iter()
+                .map(|h| (h.level, h.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(1, "Title"), (2, "Log")]
+        );
+        assert_eq!(&SRC[headings[1].range.clone()], "## Log ##");
+    }
+
+    #[test]
+    fn parse_links() {
+        let links = links(SRC);
+        assert_eq!(links.len(), 4);
+        assert_eq!(
+            links[0],
+            Link {
+                kind: LinkKind::Wiki,
+                target: "Other note".into(),
+                subpath: Some("Part".into()),
+                alias: Some("see".into()),
+                embed: false,
+                range: 14..37,
+            }
+        );
+        assert_eq!(&SRC[links[0].range.clone()], "[[Other note#Part|see]]");
+        assert!(links[1].embed);
+        assert_eq!(
+            (links[2].kind, links[2].target.as_str()),
+            (LinkKind::Markdown, "Folder/My Note.md")
+        );
+        assert_eq!(links[2].subpath.as_deref(), Some("Log"));
+        assert_eq!(
+            (links[3].target.as_str(), links[3].subpath.as_deref()),
+            ("", Some("^block"))
+        );
+    }
+
+    #[test]
+    fn find_sections_and_blocks() {
+        let src = "# Day\n## Log\n- one ^first\n### Detail\ntext\n## Tasks\n- [ ] two\n";
+        let log = section(src, "log").unwrap();
+        assert_eq!(log.heading.text, "Log");
+        assert_eq!(&src[log.body.clone()], "- one ^first\n### Detail\ntext\n");
+        let tasks = section(src, "## Tasks").unwrap();
+        assert_eq!(&src[tasks.body], "- [ ] two\n");
+        assert_eq!(section(src, "Missing"), None);
+
+        assert_eq!(&src[block(src, "^first").unwrap()], "- one ^first");
+        assert_eq!(block(src, "fir"), None);
+    }
+}
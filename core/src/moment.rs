@@ -0,0 +1,176 @@
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime};
+
+/// A Moment.js token and how it is rendered. Most map onto a chrono
+/// specifier; the rest have no strftime equivalent and are computed.
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Chrono(&'static str),
+    Quarter,
+    Ordinal,
+    MinWeekday,
+    LocaleWeek { pad: bool },
+    LocaleWeekYear { full: bool },
+}
+
+// Longest tokens first so `YYYY` wins over `YY`
+const TOKENS: [(&str, Token); 37] = [
+    ("YYYY", Token::Chrono("%Y")),
+    ("GGGG", Token::Chrono("%G")),
+    ("gggg", Token::LocaleWeekYear { full: true }),
+    ("MMMM", Token::Chrono("%B")),
+    ("DDDD", Token::Chrono("%j")),
+    ("dddd", Token::Chrono("%A")),
+    ("MMM", Token::Chrono("%b")),
+    ("DDD", Token::Chrono("%-j")),
+    ("ddd", Token::Chrono("%a")),
+    ("SSS", Token::Chrono("%3f")),
+    ("YY", Token::Chrono("%y")),
+    ("GG", Token::Chrono("%g")),
+    ("gg", Token::LocaleWeekYear { full: false }),
+    ("MM", Token::Chrono("%m")),
+    ("DD", Token::Chrono("%d")),
+    ("Do", Token::Ordinal),
+    ("dd", Token::MinWeekday),
+    ("WW", Token::Chrono("%V")),
+    ("ww", Token::LocaleWeek { pad: true }),
+    ("HH", Token::Chrono("%H")),
+    ("hh", Token::Chrono("%I")),
+    ("mm", Token::Chrono("%M")),
+    ("ss", Token::Chrono("%S")),
+    ("M", Token::Chrono("%-m")),
+    ("D", Token::Chrono("%-d")),
+    ("d", Token::Chrono("%w")),
+    ("E", Token::Chrono("%u")),
+    ("W", Token::Chrono("%-V")),
+    ("w", Token::LocaleWeek { pad: false }),
+    ("H", Token::Chrono("%-H")),
+    ("h", Token::Chrono("%-I")),
+    ("m", Token::Chrono("%-M")),
+    ("s", Token::Chrono("%-S")),
+    ("A", Token::Chrono("%p")),
+    ("a", Token::Chrono("%P")),
+    ("X", Token::Chrono("%s")),
+    ("Q", Token::Quarter),
+];
+
+/// Formats `dt` with a Moment.js format string, as used by Obsidian's
+/// daily notes and templates settings. `[...]` is copied literally.
+pub fn format(dt: &NaiveDateTime, fmt: &str) -> String {
+    dt.format(&to_chrono(dt.date(), fmt)).to_string()
+}
+
+/// Translates a Moment.js format into a chrono one. Tokens without a
+/// chrono specifier are rendered for `date` and inlined as literals.
+fn to_chrono(date: NaiveDate, fmt: &str) -> String {
+    let mut out = String::new();
+    let mut rest = fmt;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            let end = rest.find(']').unwrap_or(rest.len());
+            push_literal(&mut out, &rest[1..end]);
+            rest = &rest[(end + 1).min(rest.len())..];
+            continue;
+        }
+        match TOKENS.iter().find(|(tok, _)| rest.starts_with(tok)) {
+            Some((tok, token)) => {
+                render(&mut out, date, *token);
+                rest = &rest[tok.len()..];
+            }
+            None => {
+                push_literal(&mut out, &rest[..c.len_utf8()]);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+fn render(out: &mut String, date: NaiveDate, token: Token) {
+    match token {
+        Token::Chrono(spec) => out.push_str(spec),
+        Token::Quarter => push_literal(out, &date.quarter().to_string()),
+        Token::Ordinal => {
+            let day = date.day();
+            let suffix = match (day % 10, day % 100) {
+                (_, 11..=13) => "th",
+                (1, _) => "st",
+                (2, _) => "nd",
+                (3, _) => "rd",
+                _ => "th",
+            };
+            push_literal(out, &format!("{day}{suffix}"));
+        }
+        Token::MinWeekday => {
+            let day = date.format("%a").to_string();
+            push_literal(out, &day[..2]);
+        }
+        Token::LocaleWeek { pad } => {
+            let (_, week) = locale_week(date);
+            let week = if pad {
+                format!("{week:02}")
+            } else {
+                week.to_string()
+            };
+            push_literal(out, &week);
+        }
+        Token::LocaleWeekYear { full } => {
+            let (year, _) = locale_week(date);
+            let year = if full {
+                year.to_string()
+            } else {
+                format!("{:02}", year % 100)
+            };
+            push_literal(out, &year);
+        }
+    }
+}
+
+/// Week-year and week of Moment's default (en) locale: weeks start on
+/// Sunday and week 1 is the one containing January 1st.
+pub fn locale_week(date: NaiveDate) -> (i32, u32) {
+    let start = date - Days::new(date.weekday().num_days_from_sunday().into());
+    let year = (start + Days::new(6)).year();
+    let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or(date);
+    let first = jan1 - Days::new(jan1.weekday().num_days_from_sunday().into());
+    let week = (start - first).num_days() / 7 + 1;
+    (year, week as u32)
+}
+
+fn push_literal(out: &mut String, text: &str) {
+    out.push_str(&text.replace('%', "%%"));
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::moment::format;
+
+    #[test]
+    fn obsidian_formats() {
+        let dt = NaiveDate::from_ymd_opt(2025, 8, 3)
+            .unwrap()
+            .and_hms_opt(9, 5, 0)
+            .unwrap();
+
+        assert_eq!(format(&dt, "YYYY-MM-DD"), "2025-08-03");
+        assert_eq!(format(&dt, "dddd, MMMM Do YYYY"), "Sunday, August 3rd 2025");
+        assert_eq!(format(&dt, "YYYY/MM/[Log] D"), "2025/08/Log 3");
+        assert_eq!(format(&dt, "HH:mm"), "09:05");
+        assert_eq!(format(&dt, "YYYY-[Q]Q"), "2025-Q3");
+        assert_eq!(format(&dt, "gggg-[W]ww"), "2025-W32");
+        assert_eq!(format(&dt, "GGGG-[W]WW"), "2025-W31");
+        assert_eq!(format(&dt, "100% [100%]"), "100% 100%");
+    }
+
+    #[test]
+    fn locale_week_year_boundary() {
+        // Sat 2022-01-01 is in the week of Sun 2021-12-26, which holds Jan 1st
+        let dt = NaiveDate::from_ymd_opt(2021, 12, 27)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(format(&dt, "gggg-[W]ww"), "2022-W01");
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use chrono::{
+    NaiveDateTime,
+    format::{Item, StrftimeItems},
+};
+use std::fmt::Write;
+
+use crate::{
+    moment,
+    template::{
+        Context, Template,
+        parser::{self, Node},
+    },
+};
+
+/// Obsidian refuses these in file names or links to them
+pub const FORBIDDEN_CHARS: &str = "[]#^|\\/:*\"<>?";
+pub const UNTITLED: &str = "Untitled";
+/// Moment.js format of `{{id}}`, a Zettelkasten timestamp ID
+pub const ID_FORMAT: &str = "YYYYMMDDHHmmss";
+const MAX_SLUG_LEN: usize = 60;
+
+/// Variables a file name pattern can use besides `{{date}}` and `{{time}}`
+#[derive(Debug, Clone, Default)]
+pub struct NameVars<'a> {
+    /// `{{title}}`, the first line of the idea if not given
+    pub title: Option<&'a str>,
+    /// The note's text, `{{slug}}` is made from its first line
+    pub idea: &'a str,
+}
+
+/// File name without `.md` from a `note_name` pattern. Patterns with
+/// `{{...}}` use template variables, others are chrono formats like
+/// `Note_%Y_%m_%d`. A given title is the whole name when the pattern has
+/// no `{{title}}`. Characters Obsidian forbids are removed.
+pub fn render(pattern: &str, now: NaiveDateTime, vars: &NameVars) -> Result<String> {
+    let templated = pattern.contains("{{");
+    let uses_title = templated && uses_var(&parser::parse(pattern)?, "title");
+    let name = if let Some(title) = vars.title.filter(|_| !uses_title) {
+        title.to_string()
+    } else if templated {
+        let first_line = first_line(vars.idea);
+        let mut context = Context::new(now);
+        context.insert("id", moment::format(&now, ID_FORMAT));
+        context.insert("title", vars.title.unwrap_or(first_line));
+        context.insert("slug", slugify(vars.title.unwrap_or(first_line)));
+        Template {
+            template: pattern.to_string(),
+            ..Default::default()
+        }
+        .render(&context)?
+    } else {
+        let mut name = String::new();
+        write!(name, "{}", now.format(pattern))
+            .map_err(|_| anyhow::Error::msg("Invalid note name format"))?;
+        name
+    };
+    Ok(sanitize(&name))
+}
+
+/// Whether `{{name}}` or a condition on it appears anywhere in `nodes`
+fn uses_var(nodes: &[Node], name: &str) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Var { name: var, .. } => var == name,
+        Node::If {
+            cond,
+            then,
+            otherwise,
+        } => cond.name == name || uses_var(then, name) || uses_var(otherwise, name),
+        Node::For { list, body, .. } => list == name || uses_var(body, name),
+        Node::Text(_) | Node::Include { .. } => false,
+    })
+}
+
+/// Checks a pattern before it is stored in the config
+pub fn validate(pattern: &str) -> Result<()> {
+    if pattern.contains("{{") {
+        parser::parse(pattern)?;
+    } else if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+        return Err(anyhow::Error::msg("Invalid note name format"));
+    }
+    Ok(())
+}
+
+/// Drops characters Obsidian forbids, using `-` for path separators and
+/// `:`, and falls back to `Untitled` when nothing is left
+pub fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .filter_map(|c| match c {
+            '/' | '\\' | ':' => Some('-'),
+            c if FORBIDDEN_CHARS.contains(c) || c.is_control() => None,
+            c => Some(c),
+        })
+        .collect::<String>();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name = name.trim_matches('.').trim();
+    if name.is_empty() {
+        UNTITLED.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `Call Bob about Q3!` -> `call-bob-about-q3`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= MAX_SLUG_LEN {
+            break;
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// The first line with text, without heading or list markers
+fn first_line(text: &str) -> &str {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches(['#', '-', '*', '>']).trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or(UNTITLED)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::naming::{NameVars, render, sanitize, slugify, validate};
+
+    #[test]
+    fn render_patterns() {
+        let now = NaiveDate::from_ymd_opt(2025, 8, 15)
+            .unwrap()
+            .and_hms_opt(14, 30, 5)
+            .unwrap();
+        let idea = NameVars {
+            title: None,
+            idea: "\n# Call Bob: Q3 [plan]?\nmore",
+        };
+        let render = |pattern| render(pattern, now, &idea).unwrap();
+
+        assert_eq!(render("Note_%Y_%m_%d_%H_%M_%S"), "Note_2025_08_15_14_30_05");
+        assert_eq!(
+            render("{{date:YYYYMMDDHHmm}} {{title}}"),
+            "202508151430 Call Bob- Q3 plan"
+        );
+        assert_eq!(render("{{id}}"), "20250815143005");
+        assert_eq!(render("{{slug}}"), "call-bob-q3-plan");
+
+        let titled = NameVars {
+            title: Some("Weekly"),
+            idea: "",
+        };
+        assert_eq!(
+            super::render("{{title}} {{date}}", now, &titled).unwrap(),
+            "Weekly 2025-08-15"
+        );
+        assert_eq!(super::render("Note_%Y", now, &titled).unwrap(), "Weekly");
+        assert_eq!(
+            super::render("{{date}}-subtitle", now, &titled).unwrap(),
+            "Weekly"
+        );
+        assert_eq!(
+            super::render("{{slug}}", now, &NameVars::default()).unwrap(),
+            "untitled"
+        );
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize("a/b\\c:d"), "a-b-c-d");
+        assert_eq!(sanitize(" [[x]] #tag ^id | y "), "x tag id y");
+        assert_eq!(sanitize("..."), "Untitled");
+        assert_eq!(slugify("Ünïcode — and  spaces!"), "ünïcode-and-spaces");
+    }
+
+    #[test]
+    fn validate_patterns() {
+        assert!(validate("Note_%Y").is_ok());
+        assert!(validate("Note_%Q").is_err());
+        assert!(validate("{{date}} {{title}}").is_ok());
+        assert!(validate("{{date").is_err());
+    }
+}
@@ -1,53 +1,429 @@
 use std::{
     fmt::Display,
-    fs::{File, OpenOptions},
-    io::Write,
-    path::PathBuf,
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
 };
 
 use anyhow::{Context, Result};
 
-#[derive(Debug, Clone)]
-pub struct Note<'a> {
-    pub body: Option<String>,
-    pub path: &'a PathBuf,
-    pub handle: &'a File,
-}
-
-impl<'a> Note<'a> {
-    pub fn new(handle: &'a File, path: &'a PathBuf, body: Option<String>) -> Self {
-        Self { body, handle, path }
-    }
-
-    // This opens the daily note in Obs, another func might open it for reading (aka read it)
-    pub fn open(path: PathBuf, vault_name: &str, formatted: String) -> Result<()> {
-        OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path.as_path())?;
-        open::that(format!(
-            "obsidian://open?vault={}&file={}",
-            urlencoding::encode(vault_name),
-            urlencoding::encode(&formatted)
-        ))?;
-        Ok(())
+use crate::{
+    atomic,
+    editor::{self, OpenWith},
+    frontmatter::{Frontmatter, Property},
+    markdown::{self, Heading, Link},
+};
+
+/// How inserted text is formatted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemFormat {
+    #[default]
+    Plain,
+    /// `- text`
+    Bullet,
+    /// `- [ ] text`
+    Task,
+}
+
+impl ItemFormat {
+    /// Prefixes the first line, indenting the rest under it
+    pub fn apply(&self, text: &str) -> String {
+        let prefix = match self {
+            ItemFormat::Plain => return text.to_string(),
+            ItemFormat::Bullet => "- ",
+            ItemFormat::Task => "- [ ] ",
+        };
+        let mut lines = text.trim_end_matches(['\r', '\n']).lines();
+        let first = format!("{prefix}{}", lines.next().unwrap_or_default());
+        lines.fold(first, |item, line| format!("{item}\n  {line}"))
+    }
+}
+
+/// Where `Note::insert` puts text. Without a heading or a block it goes to
+/// the end of the note, or to the start of the body with `prepend`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Insert {
+    /// Section to write into, added at the end of the note if missing.
+    /// `## Log` sets the level of a new heading, plain names get `##`.
+    pub heading: Option<String>,
+    /// Block id, with or without the `^`, to write right after
+    pub after_block: Option<String>,
+    /// Write at the start of the section or body instead of its end
+    pub prepend: bool,
+    pub format: ItemFormat,
+}
+
+/// A note read into memory. Edits only change `content` until `save`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub path: PathBuf,
+    pub content: String,
+    /// Flush writes to disk before returning
+    pub fsync: bool,
+}
+
+impl Note {
+    pub fn new(path: PathBuf, content: String) -> Self {
+        Self {
+            path,
+            content,
+            fsync: false,
+        }
+    }
+
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
     }
 
-    pub fn append(&mut self, idea: &str) -> Result<()> {
-        let _ = self.handle.write(b"\n")?;
-        self.handle.write_all(idea.as_bytes())?;
+    /// Writes the note as a new file with its parent folders. An existing
+    /// note is never replaced, `path` gets a ` 1`, ` 2`... suffix instead.
+    pub fn create(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.path = atomic::create_new(&self.path, self.content.as_bytes(), self.fsync)?;
         Ok(())
     }
 
-    pub fn write_file_handle(&mut self) -> Result<()> {
-        self.handle
-            .write_all(self.body.clone().context("No body to write")?.as_bytes())?;
+    /// Creates the file at exactly its path, returning false if it exists
+    pub fn create_exact(&self) -> Result<bool> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        atomic::create_exact(&self.path, self.content.as_bytes(), self.fsync)
+    }
+
+    /// Reads a note from a path relative to the vault
+    pub fn load(vault: &Path, path: &Path) -> Result<Self> {
+        let abs_path = vault.join(path);
+        if !abs_path.is_file() {
+            return Err(anyhow::Error::msg("Invalid note path, not a note"));
+        }
+        let content = fs::read_to_string(abs_path.as_path())
+            .with_context(|| format!("Could not read note {}", abs_path.display()))?;
+        Ok(Self::new(abs_path, content))
+    }
+
+    /// Replaces the file atomically, so it is never seen half-written
+    pub fn save(&self) -> Result<()> {
+        atomic::write(&self.path, self.content.as_bytes(), self.fsync)
+            .with_context(|| format!("Could not write note {}", self.path.display()))
+    }
+
+    /// Opens the note in Obsidian or an editor, at a `Heading` or `^block`
+    pub fn open(&self, vault: &Path, subpath: Option<&str>, with: &OpenWith) -> Result<()> {
+        let line = subpath
+            .and_then(|subpath| self.subpath(subpath))
+            .map(|range| editor::line_of(&self.content, range.start));
+        with.open(vault, &self.path, subpath, line)
+    }
+
+    pub fn append(&mut self, idea: &str) {
+        self.content.push('\n');
+        self.content.push_str(idea);
+    }
+
+    /// Inserts text as its own line(s) at the position `at` describes
+    pub fn insert(&mut self, text: &str, at: &Insert) -> Result<()> {
+        let text = at.format.apply(text);
+        let start = self.body_start();
+        let body = self.body();
+
+        let pos = if let Some(id) = &at.after_block {
+            let block = markdown::block(body, id)
+                .with_context(|| format!("No block ^{} in {self}", id.trim_start_matches('^')))?;
+            start + markdown::line_end(body, block.end)
+        } else if let Some(heading) = &at.heading {
+            match markdown::section(body, heading) {
+                Some(section) if at.prepend => start + section.body.start,
+                Some(section) => {
+                    // After the last line with text, before blank lines
+                    let text = &body[section.body.clone()];
+                    let end = match text.trim_end().len() {
+                        0 => 0,
+                        len => markdown::line_end(text, len),
+                    };
+                    start + section.body.start + end
+                }
+                None => {
+                    self.add_heading(heading);
+                    self.content.len()
+                }
+            }
+        } else if at.prepend {
+            start
+        } else {
+            self.append(&text);
+            return Ok(());
+        };
+
+        self.insert_line(pos, &text);
         Ok(())
     }
+
+    fn add_heading(&mut self, heading: &str) {
+        let heading = heading.trim();
+        let name = heading.trim_start_matches('#').trim();
+        let level = (heading.len() - heading.trim_start_matches('#').len()).clamp(2, 6);
+        if !self.content.is_empty() && !self.content.ends_with("\n\n") {
+            self.content.push_str(if self.content.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        self.content
+            .push_str(&format!("{} {name}\n", "#".repeat(level)));
+    }
+
+    /// Inserts `text` at a line start, or at the end on a line of its own
+    fn insert_line(&mut self, pos: usize, text: &str) {
+        let mut line = format!("{text}\n");
+        if pos > 0 && !self.content[..pos].ends_with('\n') {
+            line.insert(0, '\n');
+        }
+        self.content.insert_str(pos, &line);
+    }
+
+    /// The content after the frontmatter
+    pub fn body(&self) -> &str {
+        &self.content[self.body_start()..]
+    }
+
+    /// Headings of the body, with ranges into `content`
+    pub fn headings(&self) -> Vec<Heading> {
+        let start = self.body_start();
+        markdown::headings(self.body())
+            .into_iter()
+            .map(|mut heading| {
+                heading.range = heading.range.start + start..heading.range.end + start;
+                heading
+            })
+            .collect()
+    }
+
+    /// The line of a `Heading` or `^block` subpath, as a range into `content`
+    pub fn subpath(&self, subpath: &str) -> Option<Range<usize>> {
+        let start = self.body_start();
+        let range = match subpath.strip_prefix('^') {
+            Some(id) => markdown::block(self.body(), id)?,
+            None => markdown::section(self.body(), subpath)?.heading.range,
+        };
+        Some(range.start + start..range.end + start)
+    }
+
+    /// Links in the body, with ranges into `content`
+    pub fn links(&self) -> Vec<Link> {
+        let start = self.body_start();
+        markdown::links(self.body())
+            .into_iter()
+            .map(|mut link| {
+                link.range = link.range.start + start..link.range.end + start;
+                link
+            })
+            .collect()
+    }
+
+    /// Frontmatter properties, empty without a `---` block
+    pub fn properties(&self) -> Frontmatter {
+        Frontmatter::parse(&self.content).0
+    }
+
+    /// Sets a property, leaving the rest of the note as it was
+    pub fn set_property(&mut self, key: &str, val: Property) {
+        self.edit_properties(|props| props.set(key, val));
+    }
+
+    /// Returns whether the property was there. The frontmatter is dropped
+    /// with its last property.
+    pub fn remove_property(&mut self, key: &str) -> bool {
+        let mut removed = false;
+        self.edit_properties(|props| removed = props.remove(key));
+        removed
+    }
+
+    fn edit_properties(&mut self, edit: impl FnOnce(&mut Frontmatter)) {
+        let (mut props, rest) = Frontmatter::parse(&self.content);
+        edit(&mut props);
+        self.content = format!("{props}{rest}");
+    }
+
+    fn body_start(&self) -> usize {
+        let (_, rest) = Frontmatter::parse(&self.content);
+        self.content.len() - rest.len()
+    }
 }
 
-impl<'a> Display for Note<'a> {
+/// File types Obsidian opens besides notes, so a path ending in one of them
+/// is not a note name with a dot in it
+const ATTACHMENT_EXTENSIONS: &[&str] = &[
+    "canvas", "pdf", "avif", "bmp", "gif", "jpeg", "jpg", "png", "svg", "webp", "flac", "m4a",
+    "mp3", "ogg", "wav", "3gp", "mkv", "mov", "mp4", "ogv", "webm",
+];
+
+/// `path` with `.md` added unless it already names a note or an attachment.
+/// Anything else after a dot, like in `Meeting 10.15`, is part of the name.
+pub fn note_file(path: &Path) -> PathBuf {
+    let known = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("md")
+                || ATTACHMENT_EXTENSIONS
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+        });
+    if known {
+        return path.to_path_buf();
+    }
+    let mut file = path.as_os_str().to_os_string();
+    file.push(".md");
+    PathBuf::from(file)
+}
+
+/// Whether a relative `path` stays under the folder it is joined to,
+/// without `..`, a root or a drive
+pub fn stays_inside(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+impl Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.path.display())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use crate::{
+        frontmatter::Property,
+        note::{Insert, ItemFormat, Note, note_file},
+    };
+
+    #[test]
+    fn note_file_names() {
+        let file = |name: &str| note_file(Path::new(name));
+        assert_eq!(file("Plan"), Path::new("Plan.md"));
+        assert_eq!(file("Projects/Plan.md"), Path::new("Projects/Plan.md"));
+        assert_eq!(file("Meeting 10.15"), Path::new("Meeting 10.15.md"));
+        assert_eq!(file("v1.2/Notes 3.5"), Path::new("v1.2/Notes 3.5.md"));
+        assert_eq!(file("Board.canvas"), Path::new("Board.canvas"));
+        assert_eq!(file("assets/Photo.JPG"), Path::new("assets/Photo.JPG"));
+    }
+
+    #[test]
+    fn edit_properties() {
+        let mut note = Note::new("note.md".into(), "# Note\n".to_string());
+        assert_eq!(note.properties().iter().count(), 0);
+
+        note.set_property("status", Property::from("draft"));
+        assert_eq!(note.content, "---\nstatus: draft\n---\n# Note\n");
+        assert_eq!(
+            note.properties().get("status"),
+            Some(Property::from("draft"))
+        );
+
+        assert!(note.remove_property("status"));
+        assert!(!note.remove_property("status"));
+        assert_eq!(note.content, "# Note\n");
+    }
+
+    #[test]
+    fn create_load_save() {
+        let vault = env::temp_dir().join("obsidian-rust-cli-note");
+        let _ = fs::remove_dir_all(&vault);
+        let content = "---\n# comment\ntags: [a]\n---\n# Title\nSee [[Other]]\n";
+        let mut created = Note::new(vault.join("folder/note.md"), content.to_string());
+        created.create().unwrap();
+        let mut again = created.clone();
+        again.create().unwrap();
+        assert_eq!(again.path, vault.join("folder/note 1.md"));
+
+        let mut note = Note::load(&vault, Path::new("folder/note.md")).unwrap();
+        assert_eq!(note.body(), "# Title\nSee [[Other]]\n");
+        let headings = note.headings();
+        assert_eq!(headings.len(), 1);
+        assert_eq!(&note.content[headings[0].range.clone()], "# Title");
+        assert_eq!(&note.content[note.links()[0].range.clone()], "[[Other]]");
+        assert_eq!(&note.content[note.subpath("title").unwrap()], "# Title");
+        assert_eq!(note.subpath("^missing"), None);
+
+        note.append("more");
+        note.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&note.path).unwrap(),
+            format!("{content}\nmore")
+        );
+        assert!(Note::load(&vault, Path::new("missing.md")).is_err());
+        fs::remove_dir_all(&vault).unwrap();
+    }
+
+    #[test]
+    fn insert_into_sections() {
+        let mut note = Note::new(
+            "daily.md".into(),
+            "---\ntags: [daily]\n---\n# Day\n## Log\n- 9:00 ^start\n- 10:00\n\n## Tasks\n"
+                .to_string(),
+        );
+        let log = |prepend| Insert {
+            heading: Some("Log".into()),
+            prepend,
+            format: ItemFormat::Bullet,
+            ..Default::default()
+        };
+        note.insert("11:00", &log(false)).unwrap();
+        note.insert("8:00", &log(true)).unwrap();
+        note.insert(
+            "call Bob\nabout it",
+            &Insert {
+                heading: Some("Tasks".into()),
+                format: ItemFormat::Task,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        note.insert(
+            "9:30",
+            &Insert {
+                after_block: Some("^start".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        note.insert(
+            "top",
+            &Insert {
+                prepend: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        note.insert(
+            "idea",
+            &Insert {
+                heading: Some("### Ideas".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            note.content,
+            "---\ntags: [daily]\n---\ntop\n# Day\n## Log\n- 8:00\n- 9:00 ^start\n9:30\n- 10:00\n- 11:00\n\n## Tasks\n- [ ] call Bob\n  about it\n\n### Ideas\nidea\n"
+        );
+        assert!(
+            note.insert(
+                "x",
+                &Insert {
+                    after_block: Some("missing".into()),
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const OBSIDIAN_DIR: &str = ".obsidian";
+pub const DEFAULT_DAILY_FORMAT: &str = "YYYY-MM-DD";
+pub const PERIODIC_NOTES_FILE: &str = "plugins/periodic-notes/data.json";
+
+/// `.obsidian/daily-notes.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DailyNotesSettings {
+    pub folder: Option<String>,
+    pub format: Option<String>,
+    pub template: Option<String>,
+}
+
+/// `.obsidian/templates.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TemplatesSettings {
+    pub folder: Option<String>,
+    pub date_format: Option<String>,
+    pub time_format: Option<String>,
+}
+
+/// One period of `.obsidian/plugins/periodic-notes/data.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PeriodSettings {
+    pub enabled: bool,
+    pub folder: Option<String>,
+    pub format: Option<String>,
+    pub template: Option<String>,
+}
+
+impl PeriodSettings {
+    pub fn folder(&self) -> Option<PathBuf> {
+        non_empty(&self.folder).map(PathBuf::from)
+    }
+
+    pub fn format(&self) -> Option<&str> {
+        non_empty(&self.format)
+    }
+
+    /// Vault-relative template, stored without `.md` like the daily one
+    pub fn template(&self) -> Option<PathBuf> {
+        non_empty(&self.template).map(with_md_extension)
+    }
+}
+
+/// `.obsidian/plugins/periodic-notes/data.json` of the Periodic Notes plugin
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PeriodicNotesSettings {
+    pub daily: PeriodSettings,
+    pub weekly: PeriodSettings,
+    pub monthly: PeriodSettings,
+    pub quarterly: PeriodSettings,
+    pub yearly: PeriodSettings,
+}
+
+/// The parts of `.obsidian/app.json` that decide where files end up
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppSettings {
+    pub new_file_location: Option<String>,
+    pub new_file_folder_path: Option<String>,
+    /// "Default location for new attachments": empty for the vault root,
+    /// `./` for the note's folder, `./sub` below it, or a vault folder
+    pub attachment_folder_path: Option<String>,
+    /// "New link format": `shortest`, `relative` or `absolute`
+    pub new_link_format: Option<String>,
+    /// "Excluded files", folder paths or `/regex/` patterns
+    pub user_ignore_filters: Option<Vec<String>>,
+}
+
+/// How Obsidian names the target of a new link
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkFormat {
+    /// The file name when it is unique, else the vault path
+    #[default]
+    Shortest,
+    /// Path from the linking note's folder
+    Relative,
+    /// Vault path
+    Absolute,
+}
+
+/// Settings the Obsidian app stores in the vault. Missing files are
+/// treated as Obsidian's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ObsidianSettings {
+    pub dir: PathBuf,
+    pub daily_notes: DailyNotesSettings,
+    pub templates: TemplatesSettings,
+    pub periodic_notes: PeriodicNotesSettings,
+    pub app: AppSettings,
+}
+
+impl ObsidianSettings {
+    pub fn load(vault: &Path) -> Result<Self> {
+        let dir = vault.join(OBSIDIAN_DIR);
+        Ok(Self {
+            daily_notes: read_json(&dir.join("daily-notes.json"))?,
+            templates: read_json(&dir.join("templates.json"))?,
+            periodic_notes: read_json(&dir.join(PERIODIC_NOTES_FILE))?,
+            app: read_json(&dir.join("app.json"))?,
+            dir,
+        })
+    }
+
+    /// The Periodic Notes plugin takes over daily notes when it has them
+    /// enabled, otherwise the core Daily notes plugin's settings apply
+    pub fn daily_folder(&self) -> Option<PathBuf> {
+        match self.period("daily") {
+            Some(daily) => daily.folder(),
+            None => non_empty(&self.daily_notes.folder).map(PathBuf::from),
+        }
+    }
+
+    /// Moment.js format of daily note names
+    pub fn daily_format(&self) -> Option<&str> {
+        match self.period("daily") {
+            Some(daily) => daily.format(),
+            None => non_empty(&self.daily_notes.format),
+        }
+    }
+
+    /// Vault-relative template of daily notes. Obsidian stores it without `.md`.
+    pub fn daily_template(&self) -> Option<PathBuf> {
+        match self.period("daily") {
+            Some(daily) => daily.template(),
+            None => non_empty(&self.daily_notes.template).map(with_md_extension),
+        }
+    }
+
+    /// The file the daily note settings come from
+    pub fn daily_settings_file(&self) -> PathBuf {
+        match self.period("daily") {
+            Some(_) => self.settings_file(PERIODIC_NOTES_FILE),
+            None => self.settings_file("daily-notes.json"),
+        }
+    }
+
+    pub fn templates_folder(&self) -> Option<PathBuf> {
+        non_empty(&self.templates.folder).map(PathBuf::from)
+    }
+
+    /// Folder for new notes, only set when "Default location for new notes"
+    /// is a specified folder. "Same folder as current file" has no meaning
+    /// outside the app and falls back to the vault root.
+    pub fn new_note_folder(&self) -> Option<PathBuf> {
+        match self.app.new_file_location.as_deref() {
+            Some("folder") => non_empty(&self.app.new_file_folder_path).map(PathBuf::from),
+            _ => None,
+        }
+    }
+
+    /// Vault-relative folders of "Excluded files". Regex filters only apply
+    /// inside the app and are skipped.
+    pub fn excluded_folders(&self) -> Vec<PathBuf> {
+        self.app
+            .user_ignore_filters
+            .iter()
+            .flatten()
+            .filter(|filter| {
+                !(filter.len() > 1 && filter.starts_with('/') && filter.ends_with('/'))
+            })
+            .map(|filter| filter.trim_matches('/'))
+            .filter(|filter| !filter.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Settings of a period the Periodic Notes plugin has enabled, by its
+    /// key in the plugin's settings (`weekly`, `monthly`...)
+    pub fn period(&self, key: &str) -> Option<&PeriodSettings> {
+        let notes = &self.periodic_notes;
+        let period = match key {
+            "daily" => &notes.daily,
+            "weekly" => &notes.weekly,
+            "monthly" => &notes.monthly,
+            "quarterly" => &notes.quarterly,
+            "yearly" => &notes.yearly,
+            _ => return None,
+        };
+        period.enabled.then_some(period)
+    }
+
+    /// Vault-relative folder Obsidian puts attachments of the note at
+    /// `note` in
+    pub fn attachment_folder(&self, note: &Path) -> PathBuf {
+        let folder = self.app.attachment_folder_path.as_deref().unwrap_or("");
+        match folder.strip_prefix("./") {
+            Some(sub) => note
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(sub.trim_matches('/')),
+            None => PathBuf::from(folder.trim_matches('/')),
+        }
+    }
+
+    /// How links to moved files are rewritten
+    pub fn link_format(&self) -> LinkFormat {
+        match self.app.new_link_format.as_deref() {
+            Some("relative") => LinkFormat::Relative,
+            Some("absolute") => LinkFormat::Absolute,
+            _ => LinkFormat::Shortest,
+        }
+    }
+
+    pub fn settings_file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
+fn read_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.is_file() {
+        return Ok(T::default());
+    }
+    let buf = fs::read_to_string(path)?;
+    serde_json::from_str(&buf)
+        .with_context(|| format!("Invalid Obsidian settings {}", path.display()))
+}
+
+fn non_empty(val: &Option<String>) -> Option<&str> {
+    val.as_deref()
+        .map(|val| val.trim_matches('/'))
+        .filter(|val| !val.is_empty())
+}
+
+fn with_md_extension(path: &str) -> PathBuf {
+    if path.ends_with(".md") {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(format!("{path}.md"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+    };
+
+    use crate::obsidian::{LinkFormat, ObsidianSettings, PERIODIC_NOTES_FILE};
+
+    #[test]
+    fn load_settings() {
+        let vault = env::temp_dir().join("obsidian-rust-cli-settings");
+        let dir = vault.join(".obsidian");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("daily-notes.json"),
+            r#"{"folder": "Journal/", "format": "YYYY/MM/YYYY-MM-DD", "template": "Templates/Daily"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("app.json"),
+            r#"{"newFileLocation": "folder", "newFileFolderPath": "Inbox", "alwaysUpdateLinks": true, "newLinkFormat": "relative", "attachmentFolderPath": "./assets", "userIgnoreFilters": ["Archive/", "/\\.tmp$/"]}"#,
+        )
+        .unwrap();
+        let _ = fs::remove_file(dir.join("templates.json"));
+        fs::create_dir_all(dir.join("plugins/periodic-notes")).unwrap();
+        fs::write(
+            dir.join(PERIODIC_NOTES_FILE),
+            r#"{"weekly": {"enabled": true, "format": "gggg-[W]ww", "folder": "Weekly/", "template": ""}, "monthly": {"enabled": false, "folder": "Monthly"}}"#,
+        )
+        .unwrap();
+
+        let settings = ObsidianSettings::load(&vault).unwrap();
+        assert_eq!(settings.daily_folder(), Some(PathBuf::from("Journal")));
+        assert_eq!(settings.daily_format(), Some("YYYY/MM/YYYY-MM-DD"));
+        assert_eq!(
+            settings.daily_template(),
+            Some(PathBuf::from("Templates/Daily.md"))
+        );
+        assert_eq!(settings.new_note_folder(), Some(PathBuf::from("Inbox")));
+        assert_eq!(settings.templates_folder(), None);
+        assert_eq!(settings.excluded_folders(), vec![PathBuf::from("Archive")]);
+        assert_eq!(settings.link_format(), LinkFormat::Relative);
+        assert_eq!(
+            settings.attachment_folder(Path::new("Projects/Plan.md")),
+            PathBuf::from("Projects/assets")
+        );
+        assert_eq!(
+            ObsidianSettings::default().attachment_folder(Path::new("Projects/Plan.md")),
+            PathBuf::new()
+        );
+        let weekly = settings.period("weekly").unwrap();
+        assert_eq!(weekly.folder(), Some(PathBuf::from("Weekly")));
+        assert_eq!(weekly.format(), Some("gggg-[W]ww"));
+        assert_eq!(weekly.template(), None);
+        assert!(settings.period("monthly").is_none());
+        assert_eq!(settings.daily_settings_file(), dir.join("daily-notes.json"));
+
+        // Daily notes enabled in Periodic Notes win over the core plugin
+        fs::write(
+            dir.join(PERIODIC_NOTES_FILE),
+            r#"{"daily": {"enabled": true, "format": "DD.MM.YYYY", "folder": "Days"}}"#,
+        )
+        .unwrap();
+        let settings = ObsidianSettings::load(&vault).unwrap();
+        assert_eq!(settings.daily_folder(), Some(PathBuf::from("Days")));
+        assert_eq!(settings.daily_format(), Some("DD.MM.YYYY"));
+        assert_eq!(settings.daily_template(), None);
+        assert_eq!(
+            settings.daily_settings_file(),
+            dir.join(PERIODIC_NOTES_FILE)
+        );
+
+        fs::remove_dir_all(&vault).unwrap();
+    }
+}
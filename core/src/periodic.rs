@@ -0,0 +1,324 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use anyhow::Result;
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveTime, Weekday};
+
+use crate::{
+    config::Config,
+    moment,
+    note::{Insert, Note},
+    template::Template,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Period {
+    pub const ALL: [Period; 5] = [
+        Period::Daily,
+        Period::Weekly,
+        Period::Monthly,
+        Period::Quarterly,
+        Period::Yearly,
+    ];
+
+    /// The key in the Periodic Notes settings, also used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+            Period::Monthly => "monthly",
+            Period::Quarterly => "quarterly",
+            Period::Yearly => "yearly",
+        }
+    }
+
+    /// The Periodic Notes plugin's default file name format
+    pub fn default_format(&self) -> &'static str {
+        match self {
+            Period::Daily => "YYYY-MM-DD",
+            Period::Weekly => "gggg-[W]ww",
+            Period::Monthly => "YYYY-MM",
+            Period::Quarterly => "YYYY-[Q]Q",
+            Period::Yearly => "YYYY",
+        }
+    }
+
+    /// `date` moved by `offset` periods, `None` when out of range
+    pub fn shift(&self, date: NaiveDate, offset: i32) -> Option<NaiveDate> {
+        let n = offset.unsigned_abs();
+        let days = |days: u32| match offset < 0 {
+            true => date.checked_sub_days(Days::new(days.into())),
+            false => date.checked_add_days(Days::new(days.into())),
+        };
+        let months = |months: u32| match offset < 0 {
+            true => date.checked_sub_months(Months::new(months)),
+            false => date.checked_add_months(Months::new(months)),
+        };
+        match self {
+            Period::Daily => days(n),
+            Period::Weekly => days(n.checked_mul(7)?),
+            Period::Monthly => months(n),
+            Period::Quarterly => months(n.checked_mul(3)?),
+            Period::Yearly => months(n.checked_mul(12)?),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Period {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Period::ALL
+            .into_iter()
+            .find(|period| period.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names = Period::ALL.map(|period| period.name());
+                anyhow::Error::msg(format!(
+                    "Unknown period `{s}` (expected one of: {})",
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Where the notes of a period live and what they are created from
+#[derive(Debug, Clone)]
+pub struct PeriodicNote {
+    pub period: Period,
+    /// Vault-relative folder
+    pub folder: PathBuf,
+    /// Moment.js format of the file name, which may add subfolders
+    pub format: String,
+    pub template: Option<Template>,
+}
+
+impl PeriodicNote {
+    /// Daily notes follow the daily notes settings. Other periods use the
+    /// Periodic Notes plugin's settings when it has them enabled, otherwise
+    /// its defaults in the vault root without a template.
+    pub fn resolve(cfg: &Config, period: Period) -> Result<Self> {
+        if period == Period::Daily {
+            return Ok(Self {
+                period,
+                folder: cfg.daily_folder.clone(),
+                format: cfg.daily_format.clone(),
+                template: cfg.daily_template.clone(),
+            });
+        }
+
+        let settings = cfg.obsidian.period(period.name());
+        let mut template = None;
+        if let Some(path) = settings.and_then(|s| s.template()) {
+            let mut templ = Template {
+                path: cfg.vault.join(path),
+                include_dir: cfg.templates_folder.clone(),
+                ..Default::default()
+            };
+            if templ.path.is_file() {
+                templ.parse_string()?;
+                template = Some(templ);
+            }
+        }
+        Ok(Self {
+            period,
+            folder: settings.and_then(|s| s.folder()).unwrap_or_default(),
+            format: settings
+                .and_then(|s| s.format())
+                .unwrap_or(period.default_format())
+                .to_string(),
+            template,
+        })
+    }
+
+    /// First day of the period containing `date`. Weeks start on Sunday,
+    /// like Moment's default locale, or on Monday for ISO week formats.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self.period {
+            Period::Daily => date,
+            Period::Weekly => {
+                let first = match self.format.contains("GG") || self.format.contains("WW") {
+                    true => Weekday::Mon,
+                    false => Weekday::Sun,
+                };
+                date.week(first).first_day()
+            }
+            Period::Monthly => date.with_day(1).unwrap_or(date),
+            Period::Quarterly => {
+                NaiveDate::from_ymd_opt(date.year(), (date.quarter() - 1) * 3 + 1, 1)
+                    .unwrap_or(date)
+            }
+            Period::Yearly => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+
+    /// Start of the period `offset` periods away from the one with `date`
+    pub fn date(&self, date: NaiveDate, offset: i32) -> Result<NaiveDate> {
+        let shifted = self.period.shift(self.start(date), offset).ok_or_else(|| {
+            anyhow::Error::msg(format!(
+                "Date out of range: {date} {offset:+} {}",
+                self.period
+            ))
+        })?;
+        Ok(self.start(shifted))
+    }
+
+    /// The file name of the period's note, without `.md`
+    pub fn name(&self, date: NaiveDate) -> String {
+        moment::format(&self.start(date).and_time(NaiveTime::MIN), &self.format)
+    }
+
+    /// Vault-relative path of the note for the period containing `date`
+    pub fn path(&self, date: NaiveDate) -> PathBuf {
+        self.folder.join(format!("{}.md", self.name(date)))
+    }
+
+    /// Loads the period's note, first creating it from the template if it
+    /// doesn't exist. A note created meanwhile, say by sync, is reused rather
+    /// than duplicated. Returns the note and whether it was created.
+    pub fn ensure(&self, cfg: &Config, date: NaiveDate) -> Result<(Note, bool)> {
+        let path = self.path(date);
+        if cfg.vault.join(&path).is_file() {
+            return Ok((Note::load(&cfg.vault, &path)?.with_fsync(cfg.fsync), false));
+        }
+
+        let content = match &self.template {
+            Some(templ) => {
+                let title = path.file_stem().unwrap_or_default().to_string_lossy();
+                let now = self.start(date).and_time(Local::now().time());
+                templ.render(&cfg.template_context(now, &title))?
+            }
+            None => String::new(),
+        };
+        let note = Note::new(cfg.vault.join(&path), content).with_fsync(cfg.fsync);
+        if !note.create_exact()? {
+            return Ok((Note::load(&cfg.vault, &path)?.with_fsync(cfg.fsync), false));
+        }
+        Ok((note, true))
+    }
+
+    /// Inserts `text` into the period's note, which is created first unless
+    /// `create` is false. Returns the note and whether it was created.
+    pub fn append(
+        &self,
+        cfg: &Config,
+        date: NaiveDate,
+        text: &str,
+        at: &Insert,
+        create: bool,
+    ) -> Result<(Note, bool)> {
+        let path = self.path(date);
+        if !create && !cfg.vault.join(&path).is_file() {
+            return Err(anyhow::Error::msg(format!(
+                "Note does not exist: {}",
+                path.display()
+            )));
+        }
+        let (mut note, created) = self.ensure(cfg, date)?;
+        note.insert(text, at)?;
+        note.save()?;
+        Ok((note, created))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use chrono::NaiveDate;
+
+    use crate::{
+        config::Config,
+        note::Insert,
+        periodic::{Period, PeriodicNote},
+        template::Template,
+    };
+
+    fn periodic(period: Period, format: &str) -> PeriodicNote {
+        PeriodicNote {
+            period,
+            folder: PathBuf::from("Periodic"),
+            format: format.to_string(),
+            template: None,
+        }
+    }
+
+    fn name(period: Period, date: NaiveDate, offset: i32) -> String {
+        let note = periodic(period, period.default_format());
+        note.name(note.date(date, offset).unwrap())
+    }
+
+    #[test]
+    fn period_names_and_dates() {
+        // A Wednesday
+        let date = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        assert_eq!(name(Period::Daily, date, -1), "2026-10-13");
+        assert_eq!(name(Period::Weekly, date, 0), "2026-W42");
+        assert_eq!(name(Period::Weekly, date, 1), "2026-W43");
+        assert_eq!(name(Period::Monthly, date, -10), "2025-12");
+        assert_eq!(name(Period::Quarterly, date, 1), "2027-Q1");
+        assert_eq!(name(Period::Yearly, date, -1), "2025");
+
+        let iso = periodic(Period::Weekly, "GGGG-[W]WW [from] MM-DD");
+        assert_eq!(iso.name(date), "2026-W42 from 10-12");
+        let locale = periodic(Period::Weekly, "gggg-[W]ww [from] MM-DD");
+        assert_eq!(locale.name(date), "2026-W42 from 10-11");
+        assert_eq!(
+            locale.path(date),
+            PathBuf::from("Periodic/2026-W42 from 10-11.md")
+        );
+
+        // Month ends are clamped, and the result is the start of a month anyway
+        let jan31 = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        assert_eq!(name(Period::Monthly, jan31, 1), "2026-02");
+        assert_eq!("Weekly".parse::<Period>().unwrap(), Period::Weekly);
+        assert!("hourly".parse::<Period>().is_err());
+    }
+
+    #[test]
+    fn ensure_creates_from_template() {
+        let vault = env::temp_dir().join("obsidian-rust-cli-periodic");
+        let _ = fs::remove_dir_all(&vault);
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        let cfg = Config {
+            vault: vault.clone(),
+            ..Default::default()
+        };
+        let mut monthly = periodic(Period::Monthly, "YYYY/MMMM");
+        monthly.template = Some(Template {
+            template: "# {{title}}\nFrom {{date:YYYY-MM-DD}}\n".to_string(),
+            ..Default::default()
+        });
+
+        let date = NaiveDate::from_ymd_opt(2026, 3, 17).unwrap();
+        let (note, created) = monthly.ensure(&cfg, date).unwrap();
+        assert!(created);
+        assert_eq!(note.path, vault.join("Periodic/2026/March.md"));
+        assert_eq!(note.content, "# March\nFrom 2026-03-01\n");
+
+        fs::write(&note.path, "edited").unwrap();
+        let (note, created) = monthly.ensure(&cfg, date).unwrap();
+        assert!(!created);
+        assert_eq!(note.content, "edited");
+
+        let april = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        let at = Insert::default();
+        assert!(monthly.append(&cfg, april, "idea", &at, false).is_err());
+        let (note, created) = monthly.append(&cfg, april, "idea", &at, true).unwrap();
+        assert!(created);
+        assert_eq!(note.content, "# April\nFrom 2026-04-01\n\nidea");
+        fs::remove_dir_all(&vault).unwrap();
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    atomic::Transaction,
+    diff,
+    links::Resolved,
+    markdown::{Link, LinkKind},
+    note::{note_file, stays_inside},
+    obsidian::LinkFormat,
+    vault::{Attachment, Vault, is_note},
+};
+
+/// New content of a note whose links change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// Vault-relative path before the move
+    pub path: PathBuf,
+    pub old: String,
+    pub new: String,
+}
+
+/// A move of a note, attachment or folder together with the edits that
+/// keep every link to it, and every link of the moved notes, working
+#[derive(Debug, Clone)]
+pub struct Rename {
+    pub root: PathBuf,
+    /// Vault-relative paths
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Old and new path of every indexed file that moves
+    pub files: Vec<(PathBuf, PathBuf)>,
+    pub edits: Vec<Edit>,
+}
+
+impl Rename {
+    /// Plans moving `from` to `to`, both vault-relative. Notes get `.md`
+    /// when `to` has no extension, and an existing folder `to` is moved into.
+    /// Links that change name their target in `format`.
+    pub fn plan(vault: &Vault, from: &Path, to: &Path, format: LinkFormat) -> Result<Self> {
+        let from = &inside(from)?;
+        let to = &inside(to)?;
+        let is_folder = vault.folders.iter().any(|folder| folder == from);
+        let mut to = to.to_path_buf();
+        if vault.root.join(&to).is_dir() {
+            to.push(from.file_name().unwrap_or_default());
+        } else if vault.note(from).is_some() {
+            to = note_file(&to);
+        }
+        if vault.root.join(&to).exists() {
+            return Err(anyhow::Error::msg(format!(
+                "{} already exists",
+                to.display()
+            )));
+        }
+        if is_folder && to.starts_with(from) {
+            return Err(anyhow::Error::msg(format!(
+                "Can't move {} into itself",
+                from.display()
+            )));
+        }
+
+        let moved = |path: &Path| match path.strip_prefix(from) {
+            Result::Ok(rest) if is_folder => Some(to.join(rest)),
+            _ if path == from => Some(to.clone()),
+            _ => None,
+        };
+        let files = vault
+            .notes
+            .iter()
+            .map(|note| &note.path)
+            .chain(vault.attachments.iter().map(|attachment| &attachment.path))
+            .filter_map(|path| Some((path.clone(), moved(path)?)))
+            .collect::<Vec<_>>();
+        if files.is_empty() && !is_folder {
+            return Err(anyhow::Error::msg(format!(
+                "No note, attachment or folder {} in the vault",
+                from.display()
+            )));
+        }
+
+        let mut rename = Self {
+            root: vault.root.clone(),
+            from: from.to_path_buf(),
+            to,
+            files,
+            edits: vec![],
+        };
+        let after = Vault::new(
+            vault.root.clone(),
+            vault
+                .notes
+                .iter()
+                .map(|note| note.moved(rename.moved(&note.path)))
+                .collect(),
+            vault
+                .attachments
+                .iter()
+                .map(|attachment| Attachment {
+                    path: rename.moved(&attachment.path),
+                    ..attachment.clone()
+                })
+                .collect(),
+            vec![],
+        );
+
+        for note in &vault.notes {
+            let from = rename.moved(&note.path);
+            let mut changes = vec![];
+            for link in vault.resolve_links(&note.path) {
+                let target = match link.target {
+                    Resolved::Note(path) => Resolved::Note(rename.moved(&path)),
+                    Resolved::Attachment(path) => Resolved::Attachment(rename.moved(&path)),
+                    Resolved::Unresolved => continue,
+                };
+                if after.resolve(&link.link, &from) != target {
+                    changes.push((link.link, target));
+                }
+            }
+            if changes.is_empty() {
+                continue;
+            }
+
+            let path = vault.root.join(&note.path);
+            let old = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read note {}", path.display()))?;
+            let mut new = old.clone();
+            for (link, target) in changes.iter().rev() {
+                let Some(text) = old.get(link.range.clone()) else {
+                    return Err(anyhow::Error::msg(format!(
+                        "{} changed while planning the move",
+                        note.path.display()
+                    )));
+                };
+                let target = target_text(&after, link, &from, target, format);
+                let text = relink(text, link, &target);
+                new.replace_range(link.range.clone(), &text);
+            }
+            rename.edits.push(Edit {
+                path: note.path.clone(),
+                old,
+                new,
+            });
+        }
+        Ok(rename)
+    }
+
+    /// Where a vault-relative path ends up
+    pub fn moved(&self, path: &Path) -> PathBuf {
+        self.files
+            .iter()
+            .find(|(from, _)| from == path)
+            .map_or_else(|| path.to_path_buf(), |(_, to)| to.clone())
+    }
+
+    /// Unified diff of every edit, under the notes' old and new paths
+    pub fn diff(&self) -> String {
+        self.edits
+            .iter()
+            .map(|edit| {
+                diff::unified(
+                    &format!("a/{}", edit.path.display()),
+                    &format!("b/{}", self.moved(&edit.path).display()),
+                    &edit.old,
+                    &edit.new,
+                )
+            })
+            .collect()
+    }
+
+    /// Writes every edit and moves the file or folder, or does none of it
+    pub fn apply(&self, fsync: bool) -> Result<()> {
+        let mut tx = Transaction::new(fsync);
+        for edit in &self.edits {
+            tx.write(self.root.join(&edit.path), edit.new.as_str());
+        }
+        tx.rename(self.root.join(&self.from), self.root.join(&self.to));
+        tx.commit()
+    }
+}
+
+/// How the link should name its target now, in `format` when that still
+/// finds it. Links written with a folder keep one in the shortest format.
+fn target_text(
+    after: &Vault,
+    link: &Link,
+    from: &Path,
+    target: &Resolved,
+    format: LinkFormat,
+) -> String {
+    let path = target.path().unwrap_or_else(|| Path::new(""));
+    let keep_md = !matches!(target, Resolved::Note(_)) || is_note(Path::new(&link.target));
+    let unix = |path: &Path| {
+        let path = path.to_string_lossy().replace('\\', "/");
+        match keep_md {
+            true => path,
+            false => path.strip_suffix(".md").unwrap_or(&path).to_string(),
+        }
+    };
+
+    let name = unix(Path::new(path.file_name().unwrap_or_default()));
+    let full = unix(path);
+    let mut near = unix(&relative(from.parent().unwrap_or(Path::new("")), path));
+    // Wikilinks are only relative to the note with a leading `.`
+    if link.kind == LinkKind::Wiki && !near.starts_with('.') {
+        near = format!("./{near}");
+    }
+    let finds = |text: &String| {
+        let link = Link {
+            target: text.clone(),
+            ..link.clone()
+        };
+        after.resolve(&link, from) == *target
+    };
+    let candidates = match format {
+        LinkFormat::Shortest if !link.target.contains('/') => vec![name, full.clone(), near],
+        LinkFormat::Shortest | LinkFormat::Absolute => vec![full.clone(), near],
+        LinkFormat::Relative => vec![near, full.clone()],
+    };
+    let text = candidates.into_iter().find(finds).unwrap_or(full);
+    match link.kind {
+        LinkKind::Markdown => text.replace(' ', "%20"),
+        LinkKind::Wiki => text,
+    }
+}
+
+/// The link's text with only its target replaced, keeping the embed mark,
+/// subpath, alias and display text as written
+fn relink(text: &str, link: &Link, target: &str) -> String {
+    let (start, end) = match link.kind {
+        LinkKind::Wiki => {
+            let start = text.find("[[").map_or(0, |i| i + 2);
+            let end = text[start..]
+                .find(['#', '^', '|', ']'])
+                .map_or(text.len(), |i| start + i);
+            (start, end)
+        }
+        LinkKind::Markdown => {
+            let open = text.find("](").map_or(0, |i| i + 2);
+            let start = open + (text[open..].len() - text[open..].trim_start().len());
+            match text[start..].strip_prefix('<') {
+                Some(rest) => {
+                    let end = rest.find(['#', '>']).map_or(text.len(), |i| start + 1 + i);
+                    let target = target.replace("%20", " ");
+                    return format!("{}{target}{}", &text[..start + 1], &text[end..]);
+                }
+                None => {
+                    let end = text[start..]
+                        .find(|c: char| c == '#' || c == ')' || c.is_whitespace())
+                        .map_or(text.len(), |i| start + i);
+                    (start, end)
+                }
+            }
+        }
+    };
+    format!("{}{target}{}", &text[..start], &text[end..])
+}
+
+/// A vault-relative path without `.`, refusing absolute paths and `..`,
+/// which could leave the vault
+fn inside(path: &Path) -> Result<PathBuf> {
+    if !stays_inside(path) {
+        return Err(anyhow::Error::msg(format!(
+            "{} is not a path inside the vault",
+            path.display()
+        )));
+    }
+    Ok(path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect())
+}
+
+/// `to` as seen from the folder `dir`, both vault-relative
+fn relative(dir: &Path, to: &Path) -> PathBuf {
+    let dir = dir.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = dir.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..dir.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::{obsidian::LinkFormat, rename::Rename, vault::Vault};
+
+    #[test]
+    fn move_and_relink() {
+        let root = env::temp_dir().join("obsidian-rust-cli-rename");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Notes")).unwrap();
+        fs::create_dir_all(root.join("Elsewhere")).unwrap();
+        fs::write(
+            root.join("Notes/Old.md"),
+            "---\naliases: [Alt]\n---\n# Top\n[up](../Third.md) [[Third]] [[#Top]]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("Notes/Other.md"),
+            "[[Old#Top|see]] ![[Old]] [[Notes/Old]] [md](Old.md#Top) [[Alt]]\n[up](../Third.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("Third.md"),
+            "[[Old]]\n[a](<Notes/Old.md>) [[Notes/Other]]\n",
+        )
+        .unwrap();
+        fs::write(root.join("Elsewhere/New.md"), "").unwrap();
+
+        let vault = Vault::load(&root, &[]).unwrap();
+        let rename = Rename::plan(
+            &vault,
+            &PathBuf::from("Notes/Old.md"),
+            &PathBuf::from("Archive/Deep/New"),
+            LinkFormat::Shortest,
+        )
+        .unwrap();
+        assert_eq!(rename.to, PathBuf::from("Archive/Deep/New.md"));
+        let diff = rename.diff();
+        assert!(diff.contains("--- a/Notes/Old.md\n+++ b/Archive/Deep/New.md\n"));
+        assert!(diff.contains("\n-[[Old]]\n"));
+        rename.apply(false).unwrap();
+
+        assert!(!root.join("Notes/Old.md").exists());
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(
+            read("Archive/Deep/New.md"),
+            "---\naliases: [Alt]\n---\n# Top\n[up](Third.md) [[Third]] [[#Top]]\n"
+        );
+        assert_eq!(
+            read("Notes/Other.md"),
+            "[[Archive/Deep/New#Top|see]] ![[Archive/Deep/New]] [[Archive/Deep/New]] \
+             [md](Archive/Deep/New.md#Top) [[Alt]]\n[up](../Third.md)\n"
+        );
+        assert_eq!(
+            read("Third.md"),
+            "[[Archive/Deep/New]]\n[a](<Archive/Deep/New.md>) [[Notes/Other]]\n"
+        );
+
+        // Into an existing folder, where `[[Notes/Other]]` still finds it
+        let vault = Vault::load(&root, &[]).unwrap();
+        let rename = Rename::plan(
+            &vault,
+            &PathBuf::from("Notes"),
+            &PathBuf::from("Archive"),
+            LinkFormat::Shortest,
+        )
+        .unwrap();
+        assert_eq!(
+            rename.files,
+            vec![(
+                PathBuf::from("Notes/Other.md"),
+                PathBuf::from("Archive/Notes/Other.md")
+            )]
+        );
+        assert_eq!(rename.edits.len(), 1);
+        assert!(rename.edits[0].new.contains("[up](Third.md)"));
+        rename.apply(true).unwrap();
+        assert!(root.join("Archive/Notes/Other.md").is_file());
+
+        let vault = Vault::load(&root, &[]).unwrap();
+        assert!(
+            Rename::plan(
+                &vault,
+                &PathBuf::from("Third.md"),
+                &PathBuf::from("Elsewhere/New"),
+                LinkFormat::Shortest,
+            )
+            .is_err()
+        );
+        assert!(
+            Rename::plan(
+                &vault,
+                &PathBuf::from("Archive"),
+                &PathBuf::from("Archive/Deep/x"),
+                LinkFormat::Shortest,
+            )
+            .is_err()
+        );
+        for (from, to) in [
+            ("Third.md", "../escaped"),
+            ("Third.md", "/tmp/abs"),
+            ("../obsidian-rust-cli-rename/Third.md", "Moved"),
+        ] {
+            let err = Rename::plan(
+                &vault,
+                &PathBuf::from(from),
+                &PathBuf::from(to),
+                LinkFormat::Shortest,
+            )
+            .unwrap_err();
+            assert!(err.to_string().ends_with("is not a path inside the vault"));
+        }
+        assert!(
+            Rename::plan(
+                &vault,
+                &PathBuf::from("Nope.md"),
+                &PathBuf::from("x"),
+                LinkFormat::Shortest,
+            )
+            .is_err()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn relink_in_link_format() {
+        let root = env::temp_dir().join("obsidian-rust-cli-rename-format");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("A")).unwrap();
+        fs::create_dir_all(root.join("B")).unwrap();
+        fs::write(root.join("A/Note.md"), "").unwrap();
+        fs::write(root.join("B/Src.md"), "[[Note]] [n](../A/Note.md)\n").unwrap();
+
+        let vault = Vault::load(&root, &[]).unwrap();
+        let relinked = |format| {
+            let rename = Rename::plan(
+                &vault,
+                &PathBuf::from("A/Note.md"),
+                &PathBuf::from("C/My Note"),
+                format,
+            )
+            .unwrap();
+            rename.edits[0].new.clone()
+        };
+        assert_eq!(
+            relinked(LinkFormat::Shortest),
+            "[[My Note]] [n](C/My%20Note.md)\n"
+        );
+        assert_eq!(
+            relinked(LinkFormat::Relative),
+            "[[../C/My Note]] [n](../C/My%20Note.md)\n"
+        );
+        assert_eq!(
+            relinked(LinkFormat::Absolute),
+            "[[C/My Note]] [n](C/My%20Note.md)\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,22 +1,38 @@
 use std::io::Read;
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 
-#[derive(Debug, Clone, Default)]
-pub struct TemplArgs {
-    pub date: String,
-    pub body: String, // ...
-}
+pub mod context;
+pub mod frontmatter;
+pub mod library;
+pub mod parser;
+
+pub use context::{Context, Value};
+pub use library::{Library, Meta};
+
+use crate::{
+    frontmatter::Frontmatter,
+    note::stays_inside,
+    template::parser::{Condition, Node},
+};
+
+/// `{{prompt:Label}}` asks for a value stored under `Label`
+pub const PROMPT_VAR: &str = "prompt";
+
+pub const DEFAULT_TEMPLATE: &str = "# Idea {{date:YYYY-MM-DD HH:mm}}\n\n{{body}}\n";
 
 #[derive(Debug, Clone, Default)]
 pub struct Template {
     pub path: PathBuf,
     pub template: String,
+    /// Where `{% include %}` paths are resolved, the template's own folder if unset
+    pub include_dir: Option<PathBuf>,
 }
 
-// Own parser (struct?) to support more intricate
-// templates later?
 impl Template {
     pub fn parse_string(&mut self) -> Result<()> {
         let mut file = File::open(self.path.as_path())?;
@@ -24,29 +40,407 @@ impl Template {
         Ok(())
     }
 
-    // Refactor when necessary
-    pub fn render(&self, context: &TemplArgs) -> Result<String> {
-        let mut new_note_templ = self.template.clone();
-        new_note_templ = new_note_templ.replace("?time", &context.date);
-        new_note_templ = new_note_templ.replace("?body", &context.body);
-        Ok(new_note_templ)
+    /// Templates without `{{` that use `?time`/`?body` are rendered the old way
+    pub fn is_legacy(&self) -> bool {
+        !self.template.contains("{{")
+            && !self.template.contains("{%")
+            && (self.template.contains("?time") || self.template.contains("?body"))
+    }
+
+    pub fn parse(&self) -> Result<Vec<Node>> {
+        if self.is_legacy() {
+            return Ok(parser::parse_legacy(&self.template));
+        }
+        parser::parse(&self.template)
+            .with_context(|| format!("Invalid template {}", self.path.display()))
+    }
+
+    pub fn meta(&self) -> Meta {
+        Meta::parse(&self.template)
+    }
+
+    /// Values to ask for before rendering: the frontmatter's `prompts`
+    /// followed by every `{{prompt:Label}}`, without duplicates
+    pub fn prompts(&self) -> Result<Vec<String>> {
+        let mut prompts = Frontmatter::parse(&self.template)
+            .0
+            .get(frontmatter::PROMPTS_KEY)
+            .map(|prompts| prompts.to_list())
+            .unwrap_or_default();
+        collect_prompts(&self.parse()?, &mut prompts);
+        Ok(prompts)
+    }
+
+    /// Variables missing from the context are left in the output untouched
+    pub fn render(&self, context: &Context) -> Result<String> {
+        let mut out = String::new();
+        let mut stack = vec![canonical(&self.path)];
+        self.render_nodes(&self.parse()?, context, &mut stack, &mut out)?;
+        Ok(frontmatter::remove_keys(&out, &frontmatter::TEMPLATE_KEYS))
+    }
+
+    fn render_nodes(
+        &self,
+        nodes: &[Node],
+        context: &Context,
+        stack: &mut Vec<PathBuf>,
+        out: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var { name, format, raw } => {
+                    let (name, format) = match format {
+                        Some(label) if name == PROMPT_VAR => (label.as_str(), None),
+                        _ => (name.as_str(), format.as_deref()),
+                    };
+                    match context.get(name) {
+                        Some(val) => {
+                            let format = format.unwrap_or(context.default_format(name));
+                            out.push_str(&val.render(format));
+                        }
+                        None => out.push_str(raw),
+                    }
+                }
+                Node::If {
+                    cond,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if matches(cond, context) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render_nodes(branch, context, stack, out)?;
+                }
+                Node::For { item, list, body } => {
+                    let items = match context.get(list) {
+                        Some(Value::List(items)) => items.clone(),
+                        Some(val) => vec![val.clone()],
+                        None => vec![],
+                    };
+                    for val in items {
+                        let mut context = context.clone();
+                        context.insert(item.as_str(), val);
+                        self.render_nodes(body, &context, stack, out)?;
+                    }
+                }
+                Node::Include { path, line, column } => {
+                    let partial = self.include(path, stack).with_context(|| {
+                        format!("Invalid template {}: {line}:{column}", self.path.display())
+                    })?;
+                    stack.push(canonical(&partial.path));
+                    partial.render_nodes(&partial.parse()?, context, stack, out)?;
+                    stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a partial, refusing ones that would include themselves again
+    fn include(&self, path: &str, stack: &[PathBuf]) -> Result<Template> {
+        if !stays_inside(Path::new(path)) {
+            return Err(anyhow::Error::msg(format!(
+                "Partial `{path}` must be a relative path without `..`"
+            )));
+        }
+        let dir = match &self.include_dir {
+            Some(dir) => dir.as_path(),
+            None => self.path.parent().unwrap_or(Path::new("")),
+        };
+        let mut partial = Template {
+            path: dir.join(path),
+            include_dir: self.include_dir.clone(),
+            ..Default::default()
+        };
+        if !partial.path.is_file() {
+            return Err(anyhow::Error::msg(format!(
+                "Missing partial `{path}` ({})",
+                partial.path.display()
+            )));
+        }
+        let resolved = canonical(&partial.path);
+        if let Some(start) = stack.iter().position(|p| *p == resolved) {
+            let cycle = stack[start..]
+                .iter()
+                .chain([&resolved])
+                .map(|p| p.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(anyhow::Error::msg(format!("Include cycle: {cycle}")));
+        }
+        partial.parse_string()?;
+        Ok(partial)
+    }
+}
+
+fn collect_prompts(nodes: &[Node], prompts: &mut Vec<String>) {
+    for node in nodes {
+        match node {
+            Node::Var {
+                name,
+                format: Some(label),
+                ..
+            } if name == PROMPT_VAR && !prompts.contains(label) => prompts.push(label.clone()),
+            Node::If {
+                then, otherwise, ..
+            } => {
+                collect_prompts(then, prompts);
+                collect_prompts(otherwise, prompts);
+            }
+            Node::For { body, .. } => collect_prompts(body, prompts),
+            _ => {}
+        }
     }
 }
 
+/// Lists are true when non-empty, strings when non-empty and not `false`
+fn matches(cond: &Condition, context: &Context) -> bool {
+    let val = context.get(&cond.name);
+    let result = match (&cond.equals, val) {
+        (Some(expected), Some(val)) => val.render(context.default_format(&cond.name)) == *expected,
+        (Some(_), None) | (None, None) => false,
+        (None, Some(Value::Bool(val))) => *val,
+        (None, Some(Value::List(vals))) => !vals.is_empty(),
+        (None, Some(Value::Str(val))) => !val.is_empty() && val != "false",
+        (None, Some(Value::DateTime(_))) => true,
+    };
+    result != cond.negate
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::template::{TemplArgs, Template};
+    use std::{env, fs, path::PathBuf};
+
+    use chrono::NaiveDate;
+
+    use crate::template::{Context, Template, Value};
+
+    fn context() -> Context {
+        let now = NaiveDate::from_ymd_opt(2025, 8, 15)
+            .unwrap()
+            .and_hms_opt(14, 30, 0)
+            .unwrap();
+        Context::new(now)
+    }
 
     #[test]
     fn render() {
         let mut templ = Template {
             ..Default::default()
         };
-        let args = &TemplArgs {
-            date: "Hello,".to_string(),
-            body: "World".to_string(),
-        };
+        let mut args = context();
+        args.insert("date", "Hello,");
+        args.insert("body", "World");
         templ.template = "?time ?body".to_string();
-        assert_eq!(templ.render(args).unwrap(), "Hello, World".to_string())
+        assert_eq!(templ.render(&args).unwrap(), "Hello, World".to_string())
+    }
+
+    #[test]
+    fn render_obsidian_variables() {
+        let templ = Template {
+            template: "# {{title}}\n{{date}} {{time}} {{date:dddd}} {{missing}} \\{{title}}"
+                .to_string(),
+            ..Default::default()
+        };
+        let mut args = context();
+        args.insert("title", "Plan");
+        assert_eq!(
+            templ.render(&args).unwrap(),
+            "# Plan\n2025-08-15 14:30 Friday {{missing}} {{title}}"
+        );
+    }
+
+    #[test]
+    fn values_are_not_rescanned() {
+        let mut args = context();
+        args.insert("body", "?time and {{date}}");
+
+        let legacy = Template {
+            template: "?time\n?body".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            legacy.render(&args).unwrap(),
+            "2025_08_15_14_30_00\n?time and {{date}}"
+        );
+
+        let templ = Template {
+            template: "{{body}}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(templ.render(&args).unwrap(), "?time and {{date}}");
+    }
+
+    #[test]
+    fn render_reports_position() {
+        let templ = Template {
+            path: "broken.md".into(),
+            template: "line\n{{oops".to_string(),
+            ..Default::default()
+        };
+        let err = templ.render(&context()).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Invalid template broken.md: 2:1: Unclosed `{{`"
+        );
+    }
+
+    fn partials(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("obsidian-rust-cli-{name}"));
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn render_blocks() {
+        let templ = Template {
+            template: "\
+{% if project %}Project: {{project}}
+{% endif %}
+{% for a in attendees %}
+- [[{{a}}]]
+{% endfor %}
+{% if kind == \"meeting\" %}meeting{% else %}other{% endif %}"
+                .to_string(),
+            ..Default::default()
+        };
+        let mut args = context();
+        args.insert("attendees", vec!["Ann", "Bob"]);
+        args.insert("kind", "meeting");
+        assert_eq!(
+            templ.render(&args).unwrap(),
+            "- [[Ann]]\n- [[Bob]]\nmeeting"
+        );
+
+        args.insert("project", "CLI");
+        args.insert("attendees", Value::List(vec![]));
+        args.insert("kind", "1:1");
+        assert_eq!(templ.render(&args).unwrap(), "Project: CLI\nother");
+    }
+
+    #[test]
+    fn render_includes() {
+        let dir = partials(
+            "includes",
+            &[
+                (
+                    "snippets/footer.md",
+                    "-- {{title}}\n{% include \"snippets/sig.md\" %}",
+                ),
+                ("snippets/sig.md", "sig"),
+            ],
+        );
+        let templ = Template {
+            path: dir.join("meeting.md"),
+            template: "# {{title}}\n{% include \"snippets/footer.md\" %}".to_string(),
+            include_dir: Some(dir.clone()),
+        };
+        let mut args = context();
+        args.insert("title", "Standup");
+        assert_eq!(templ.render(&args).unwrap(), "# Standup\n-- Standup\nsig");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_errors() {
+        let dir = partials(
+            "include-cycle",
+            &[
+                ("a.md", "{% include \"b.md\" %}"),
+                ("b.md", "{% include \"a.md\" %}"),
+            ],
+        );
+        let templ = Template {
+            path: dir.join("a.md"),
+            template: "{% include \"b.md\" %}".to_string(),
+            include_dir: None,
+        };
+        let err = templ.render(&context()).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!(
+                "Invalid template {}: 1:1: Include cycle: a.md -> b.md -> a.md",
+                dir.join("b.md").display()
+            )
+        );
+
+        let templ = Template {
+            path: dir.join("main.md"),
+            template: "x\n  {% include \"missing.md\" %}".to_string(),
+            include_dir: None,
+        };
+        let err = templ.render(&context()).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!(
+                "Invalid template {}: 2:3: Missing partial `missing.md` ({})",
+                dir.join("main.md").display(),
+                dir.join("missing.md").display()
+            )
+        );
+
+        for path in ["../a.md", "/etc/hostname"] {
+            let templ = Template {
+                path: dir.join("main.md"),
+                template: format!("{{% include \"{path}\" %}}"),
+                include_dir: None,
+            };
+            let err = templ.render(&context()).unwrap_err();
+            assert_eq!(
+                format!("{err:#}"),
+                format!(
+                    "Invalid template {}: 1:1: Partial `{path}` must be a relative path without `..`",
+                    dir.join("main.md").display()
+                )
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn render_prompts() {
+        let templ = Template {
+            template: "---\nprompts: [project, priority]\ntags: [todo]\n---\n{{project}} {{prompt:Due date}} {{prompt:Due date}} {{prompt:Owner}}"
+                .to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            templ.prompts().unwrap(),
+            vec!["project", "priority", "Due date", "Owner"]
+        );
+
+        let mut args = context();
+        args.insert("project", "CLI");
+        args.insert("Due date", "Friday");
+        assert_eq!(
+            templ.render(&args).unwrap(),
+            "---\ntags: [todo]\n---\nCLI Friday Friday {{prompt:Owner}}"
+        );
+    }
+
+    #[test]
+    fn render_keeps_note_properties() {
+        let templ = Template {
+            template: "---\ntemplate-description: Meetings\ntemplate-folder: Meetings\ndescription: Notes of {{title}}\nfolder: shared\n---\nbody"
+                .to_string(),
+            ..Default::default()
+        };
+        let mut args = context();
+        args.insert("title", "Standup");
+        assert_eq!(
+            templ.render(&args).unwrap(),
+            "---\ndescription: Notes of Standup\nfolder: shared\n---\nbody"
+        );
     }
 }
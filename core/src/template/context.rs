@@ -0,0 +1,100 @@
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+
+use crate::moment;
+
+pub const DEFAULT_DATE_FORMAT: &str = "YYYY-MM-DD";
+pub const DEFAULT_TIME_FORMAT: &str = "HH:mm";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    DateTime(NaiveDateTime),
+    Bool(bool),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Renders the value, `format` being a Moment.js format for dates
+    pub fn render(&self, format: &str) -> String {
+        match self {
+            Value::Str(val) => val.clone(),
+            Value::DateTime(dt) => moment::format(dt, format),
+            Value::Bool(val) => val.to_string(),
+            Value::List(vals) => vals
+                .iter()
+                .map(|val| val.render(format))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(val: &str) -> Self {
+        Value::Str(val.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(val: String) -> Self {
+        Value::Str(val)
+    }
+}
+
+impl From<NaiveDateTime> for Value {
+    fn from(val: NaiveDateTime) -> Self {
+        Value::DateTime(val)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Self {
+        Value::Bool(val)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(vals: Vec<T>) -> Self {
+        Value::List(vals.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Variables available to a template. `{{date}}` and `{{time}}` render
+/// with the Obsidian templates plugin's default formats unless overridden.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    vars: BTreeMap<String, Value>,
+    pub date_format: String,
+    pub time_format: String,
+}
+
+impl Context {
+    pub fn new(now: NaiveDateTime) -> Self {
+        let mut context = Self {
+            vars: BTreeMap::new(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+        };
+        context.insert("date", now);
+        context.insert("time", now);
+        context
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Value>) {
+        self.vars.insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.vars.get(key)
+    }
+
+    /// Default format of a variable when the template gives none
+    pub fn default_format(&self, key: &str) -> &str {
+        if key == "time" {
+            &self.time_format
+        } else {
+            &self.date_format
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::frontmatter::Frontmatter;
+
+/// Keys only the CLI reads, removed from rendered notes. The template's own
+/// metadata is namespaced so notes keep their `description` and the like.
+pub const TEMPLATE_KEYS: [&str; 4] = [PROMPTS_KEY, DESCRIPTION_KEY, FOLDER_KEY, FILENAME_KEY];
+
+pub const PROMPTS_KEY: &str = "prompts";
+pub const DESCRIPTION_KEY: &str = "template-description";
+pub const FOLDER_KEY: &str = "template-folder";
+pub const FILENAME_KEY: &str = "template-filename";
+
+/// Removes `keys` from the frontmatter of `src`, dropping the whole block
+/// if nothing is left in it
+pub fn remove_keys(src: &str, keys: &[&str]) -> String {
+    let (mut props, rest) = Frontmatter::parse(src);
+    if !keys.iter().any(|key| props.get(key).is_some()) {
+        return src.to_string();
+    }
+    for key in keys {
+        props.remove(key);
+    }
+    if props.is_empty() {
+        return rest.trim_start_matches(['\r', '\n']).to_string();
+    }
+    format!("{props}{rest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::frontmatter::remove_keys;
+
+    const SRC: &str = "---\ntags: [meeting]\nprompts:\n  - project\n  - \"priority\"\nstatus: 'open'\n---\n# {{title}}\n";
+
+    #[test]
+    fn remove_template_keys() {
+        assert_eq!(
+            remove_keys(SRC, &["prompts"]),
+            "---\ntags: [meeting]\nstatus: 'open'\n---\n# {{title}}\n"
+        );
+        assert_eq!(
+            remove_keys("---\nprompts: [a]\n---\n\nbody", &["prompts"]),
+            "body"
+        );
+        assert_eq!(
+            remove_keys("no frontmatter", &["prompts"]),
+            "no frontmatter"
+        );
+    }
+}
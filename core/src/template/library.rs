@@ -0,0 +1,146 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use walkdir::WalkDir;
+
+use crate::{
+    frontmatter::Frontmatter,
+    note::stays_inside,
+    template::{Template, frontmatter},
+};
+
+/// What a template says about itself in its frontmatter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Meta {
+    pub description: Option<String>,
+    /// Vault-relative folder of notes created from the template
+    pub folder: Option<PathBuf>,
+    /// File name pattern, like `note_name`
+    pub filename: Option<String>,
+}
+
+impl Meta {
+    pub fn parse(src: &str) -> Self {
+        let (props, _) = Frontmatter::parse(src);
+        let text = |key| props.get(key).filter(|val| !val.to_string().is_empty());
+        Self {
+            description: text(frontmatter::DESCRIPTION_KEY).map(|val| val.to_string()),
+            folder: text(frontmatter::FOLDER_KEY)
+                .map(|folder| PathBuf::from(folder.to_string().trim_matches('/'))),
+            filename: text(frontmatter::FILENAME_KEY).map(|val| val.to_string()),
+        }
+    }
+}
+
+/// A folder of templates, each named by its path in the folder without `.md`
+#[derive(Debug, Clone)]
+pub struct Library {
+    pub dir: PathBuf,
+    include_dir: Option<PathBuf>,
+}
+
+impl Library {
+    pub fn new(dir: PathBuf, include_dir: Option<PathBuf>) -> Self {
+        Self { dir, include_dir }
+    }
+
+    /// Every template sorted by name, hidden files and folders skipped
+    pub fn list(&self) -> Result<Vec<(String, Template)>> {
+        let mut templates = vec![];
+        for entry in WalkDir::new(&self.dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        {
+            let entry = entry?;
+            if entry.file_type().is_file() && entry.path().extension() == Some(OsStr::new("md")) {
+                templates.push((self.name(entry.path()), self.load(entry.path())?));
+            }
+        }
+        Ok(templates)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Template> {
+        if !stays_inside(Path::new(name)) {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid template name: {name} (must be a path inside {})",
+                self.dir.display()
+            )));
+        }
+        let path = self
+            .dir
+            .join(format!("{}.md", name.trim_end_matches(".md")));
+        if !path.is_file() {
+            return Err(anyhow::Error::msg(format!(
+                "Unknown template: {name} (not found in {})",
+                self.dir.display()
+            )));
+        }
+        self.load(&path)
+    }
+
+    fn load(&self, path: &Path) -> Result<Template> {
+        let mut templ = Template {
+            path: path.to_path_buf(),
+            include_dir: self.include_dir.clone(),
+            ..Default::default()
+        };
+        templ.parse_string()?;
+        Ok(templ)
+    }
+
+    fn name(&self, path: &Path) -> String {
+        path.strip_prefix(&self.dir)
+            .unwrap_or(path)
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::template::library::{Library, Meta};
+
+    #[test]
+    fn list_templates() {
+        let dir = env::temp_dir().join("obsidian-rust-cli-library");
+        fs::create_dir_all(dir.join("work")).unwrap();
+        fs::create_dir_all(dir.join(".trash")).unwrap();
+        fs::write(
+            dir.join("work/meeting.md"),
+            "---\ntemplate-description: Weekly sync\ntemplate-folder: Meetings/\ntemplate-filename: \"%Y-%m-%d Meeting\"\n---\n# {{title}}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("idea.md"), "{{body}}").unwrap();
+        fs::write(dir.join(".trash/old.md"), "").unwrap();
+
+        let library = Library::new(dir.clone(), None);
+        let names = library
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["idea", "work/meeting"]);
+
+        let meeting = library.get("work/meeting").unwrap();
+        assert_eq!(
+            meeting.meta(),
+            Meta {
+                description: Some("Weekly sync".into()),
+                folder: Some(PathBuf::from("Meetings")),
+                filename: Some("%Y-%m-%d Meeting".into()),
+            }
+        );
+        assert!(library.get("missing").is_err());
+        assert!(library.get("../obsidian-rust-cli-library/idea").is_err());
+        assert!(library.get(&dir.join("idea").to_string_lossy()).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
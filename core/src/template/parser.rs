@@ -0,0 +1,481 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    /// `{{name}}` or `{{name:format}}`. `raw` is the source text, rendered
+    /// as-is when the variable is not in the context.
+    Var {
+        name: String,
+        format: Option<String>,
+        raw: String,
+    },
+    /// `{% if cond %}...{% else %}...{% endif %}`
+    If {
+        cond: Condition,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    /// `{% for item in list %}...{% endfor %}`
+    For {
+        item: String,
+        list: String,
+        body: Vec<Node>,
+    },
+    /// `{% include "path" %}`, with the tag's position for error messages
+    Include {
+        path: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+/// `name`, `not name`, `name == "value"` or `name != "value"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub name: String,
+    pub negate: bool,
+    pub equals: Option<String>,
+}
+
+/// A template syntax error, 1-based line and column of where it starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+const TAG_OPEN: &str = "{%";
+const TAG_CLOSE: &str = "%}";
+
+#[derive(Debug)]
+enum Token<'a> {
+    Node(Node),
+    Tag { body: &'a str, pos: usize },
+}
+
+/// Parses `{{variable}}` and `{% tag %}` syntax. `\{{` and `\{%` are literal.
+pub fn parse(src: &str) -> Result<Vec<Node>, ParseError> {
+    let mut parser = Parser {
+        src,
+        tokens: tokenize(src)?.into_iter(),
+    };
+    match parser.parse_block()? {
+        (nodes, None) => Ok(nodes),
+        (_, Some((keyword, pos))) => Err(error(src, pos, &format!("Unexpected `{keyword}`"))),
+    }
+}
+
+/// Splits the source into text, variables and tags. A tag alone on its
+/// line takes the whole line with it, so block tags leave no blank lines.
+fn tokenize(src: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    let mut tokens = vec![];
+    let mut text = String::new();
+    let mut line_has_content = false;
+    let mut pos = 0;
+
+    while pos < src.len() {
+        let rest = &src[pos..];
+        if rest.starts_with("\\{{") || rest.starts_with("\\{%") {
+            text.push_str(&rest[1..3]);
+            line_has_content = true;
+            pos += 3;
+        } else if rest.starts_with(OPEN) {
+            let end = rest
+                .find(CLOSE)
+                .ok_or_else(|| error(src, pos, "Unclosed `{{`"))?;
+            let inner = &rest[OPEN.len()..end];
+            let (name, format) = match inner.split_once(':') {
+                Some((name, format)) => (name.trim(), Some(format.trim().to_string())),
+                None => (inner.trim(), None),
+            };
+            if !is_name(name) {
+                return Err(error(
+                    src,
+                    pos + OPEN.len(),
+                    &format!("Invalid variable name `{}`", inner.trim()),
+                ));
+            }
+
+            flush(&mut tokens, &mut text);
+            tokens.push(Token::Node(Node::Var {
+                name: name.to_string(),
+                format,
+                raw: rest[..end + CLOSE.len()].to_string(),
+            }));
+            line_has_content = true;
+            pos += end + CLOSE.len();
+        } else if rest.starts_with(TAG_OPEN) {
+            let end = rest
+                .find(TAG_CLOSE)
+                .ok_or_else(|| error(src, pos, "Unclosed `{%`"))?;
+            let body = &rest[TAG_OPEN.len()..end];
+            let mut next = pos + end + TAG_CLOSE.len();
+
+            let after = &src[next..];
+            let trailing = after.len() - after.trim_start_matches([' ', '\t']).len();
+            let standalone = !line_has_content
+                && (after[trailing..].starts_with('\n') || trailing == after.len());
+            if standalone {
+                text.truncate(text.trim_end_matches([' ', '\t']).len());
+                next += trailing + usize::from(after[trailing..].starts_with('\n'));
+            }
+
+            flush(&mut tokens, &mut text);
+            tokens.push(Token::Tag { body, pos });
+            line_has_content = !standalone;
+            pos = next;
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            text.push(c);
+            if c == '\n' {
+                line_has_content = false;
+            } else if !c.is_whitespace() {
+                line_has_content = true;
+            }
+            pos += c.len_utf8();
+        }
+    }
+
+    flush(&mut tokens, &mut text);
+    Ok(tokens)
+}
+
+fn flush(tokens: &mut Vec<Token>, text: &mut String) {
+    if !text.is_empty() {
+        tokens.push(Token::Node(Node::Text(std::mem::take(text))));
+    }
+}
+
+/// A block's nodes and the tag that ended it, if any, with its position
+type Block<'a> = (Vec<Node>, Option<(&'a str, usize)>);
+
+struct Parser<'a, I: Iterator<Item = Token<'a>>> {
+    src: &'a str,
+    tokens: I,
+}
+
+impl<'a, I: Iterator<Item = Token<'a>>> Parser<'a, I> {
+    /// Parses nodes up to the first tag that ends a block (`else`, `endif`,
+    /// `endfor`), which is returned with its position for the caller to check.
+    fn parse_block(&mut self) -> Result<Block<'a>, ParseError> {
+        let mut nodes = vec![];
+
+        while let Some(token) = self.tokens.next() {
+            let (body, pos) = match token {
+                Token::Node(node) => {
+                    nodes.push(node);
+                    continue;
+                }
+                Token::Tag { body, pos } => (body, pos),
+            };
+            let mut words = body.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            let args = body.trim_start()[keyword.len()..].trim();
+
+            match keyword {
+                "if" => nodes.push(self.parse_if(args, pos)?),
+                "for" => nodes.push(self.parse_for(args, pos)?),
+                "include" => {
+                    let path = parse_string(args)
+                        .ok_or_else(|| error(self.src, pos, "Expected `include \"path\"`"))?;
+                    let (line, column) = position(self.src, pos);
+                    nodes.push(Node::Include { path, line, column });
+                }
+                "else" | "endif" | "endfor" => return Ok((nodes, Some((keyword, pos)))),
+                _ => {
+                    return Err(error(
+                        self.src,
+                        pos,
+                        &format!("Unknown tag `{}`", body.trim()),
+                    ));
+                }
+            }
+        }
+        Ok((nodes, None))
+    }
+
+    fn parse_if(&mut self, args: &str, pos: usize) -> Result<Node, ParseError> {
+        let cond = parse_condition(args)
+            .ok_or_else(|| error(self.src, pos, &format!("Invalid condition `{args}`")))?;
+
+        let (then, end) = self.parse_block()?;
+        let (otherwise, end) = match end {
+            Some(("else", _)) => self.parse_block()?,
+            end => (vec![], end),
+        };
+        match end {
+            Some(("endif", _)) => Ok(Node::If {
+                cond,
+                then,
+                otherwise,
+            }),
+            Some((keyword, end_pos)) => Err(error(
+                self.src,
+                end_pos,
+                &format!("Unexpected `{keyword}`, expected `endif`"),
+            )),
+            None => Err(error(self.src, pos, "Unclosed `if`, expected `endif`")),
+        }
+    }
+
+    fn parse_for(&mut self, args: &str, pos: usize) -> Result<Node, ParseError> {
+        let (item, list) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [item, "in", list] if is_name(item) && is_name(list) => (item, list),
+            _ => {
+                return Err(error(
+                    self.src,
+                    pos,
+                    &format!("Invalid loop `{args}`, expected `for item in list`"),
+                ));
+            }
+        };
+
+        match self.parse_block()? {
+            (body, Some(("endfor", _))) => Ok(Node::For {
+                item: item.to_string(),
+                list: list.to_string(),
+                body,
+            }),
+            (_, Some((keyword, end_pos))) => Err(error(
+                self.src,
+                end_pos,
+                &format!("Unexpected `{keyword}`, expected `endfor`"),
+            )),
+            (_, None) => Err(error(self.src, pos, "Unclosed `for`, expected `endfor`")),
+        }
+    }
+}
+
+fn parse_condition(args: &str) -> Option<Condition> {
+    let (negate, rest) = match args.strip_prefix("not ") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, args),
+    };
+    for (op, op_negate) in [("==", false), ("!=", true)] {
+        if let Some((name, val)) = rest.split_once(op) {
+            let name = name.trim();
+            return is_name(name).then_some(Condition {
+                name: name.to_string(),
+                negate: negate != op_negate,
+                equals: Some(parse_string(val.trim())?),
+            });
+        }
+    }
+    is_name(rest).then(|| Condition {
+        name: rest.to_string(),
+        negate,
+        equals: None,
+    })
+}
+
+fn parse_string(args: &str) -> Option<String> {
+    ['"', '\''].iter().find_map(|quote| {
+        args.strip_prefix(*quote)?
+            .strip_suffix(*quote)
+            .map(str::to_string)
+    })
+}
+
+/// The original `?time`/`?body` tokens, replaced in a single pass so values
+/// containing a token are left alone.
+pub fn parse_legacy(src: &str) -> Vec<Node> {
+    const TOKENS: [(&str, &str, Option<&str>); 2] = [
+        ("?time", "date", Some("YYYY_MM_DD_HH_mm_ss")),
+        ("?body", "body", None),
+    ];
+
+    let mut nodes = vec![];
+    let mut text = String::new();
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        match TOKENS.iter().find(|(tok, ..)| rest.starts_with(tok)) {
+            Some((tok, name, format)) => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(std::mem::take(&mut text)));
+                }
+                nodes.push(Node::Var {
+                    name: name.to_string(),
+                    format: format.map(str::to_string),
+                    raw: tok.to_string(),
+                });
+                rest = &rest[tok.len()..];
+            }
+            None => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        nodes.push(Node::Text(text));
+    }
+    nodes
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn position(src: &str, pos: usize) -> (usize, usize) {
+    let before = &src[..pos];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |nl| &before[nl + 1..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+fn error(src: &str, pos: usize, msg: &str) -> ParseError {
+    let (line, column) = position(src, pos);
+    ParseError {
+        line,
+        column,
+        msg: msg.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::parser::{Condition, Node, ParseError, parse, parse_legacy};
+
+    fn var(name: &str) -> Node {
+        Node::Var {
+            name: name.into(),
+            format: None,
+            raw: format!("{{{{{name}}}}}"),
+        }
+    }
+
+    #[test]
+    fn parse_variables() {
+        let nodes = parse("# {{title}}\n{{ date:YYYY-MM-DD }} \\{{raw}}").unwrap();
+        assert_eq!(
+            nodes,
+            vec![
+                Node::Text("# ".into()),
+                var("title"),
+                Node::Text("\n".into()),
+                Node::Var {
+                    name: "date".into(),
+                    format: Some("YYYY-MM-DD".into()),
+                    raw: "{{ date:YYYY-MM-DD }}".into()
+                },
+                Node::Text(" {{raw}}".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_blocks() {
+        let src = "\
+{% for a in attendees %}
+- {{a}}
+{% endfor %}
+{% if not done %}todo{% else %}done{% endif %}
+  {% include \"snippets/footer.md\" %}
+";
+        assert_eq!(
+            parse(src).unwrap(),
+            vec![
+                Node::For {
+                    item: "a".into(),
+                    list: "attendees".into(),
+                    body: vec![Node::Text("- ".into()), var("a"), Node::Text("\n".into())],
+                },
+                Node::If {
+                    cond: Condition {
+                        name: "done".into(),
+                        negate: true,
+                        equals: None
+                    },
+                    then: vec![Node::Text("todo".into())],
+                    otherwise: vec![Node::Text("done".into())],
+                },
+                Node::Text("\n".into()),
+                Node::Include {
+                    path: "snippets/footer.md".into(),
+                    line: 5,
+                    column: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_conditions() {
+        let Node::If { cond, .. } = &parse("{% if kind != 'meeting' %}{% endif %}").unwrap()[0]
+        else {
+            panic!("expected an if block");
+        };
+        assert_eq!(
+            cond,
+            &Condition {
+                name: "kind".into(),
+                negate: true,
+                equals: Some("meeting".into())
+            }
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_source() {
+        assert_eq!(
+            parse("# Title\n\nSome {{body").unwrap_err(),
+            ParseError {
+                line: 3,
+                column: 6,
+                msg: "Unclosed `{{`".into()
+            }
+        );
+        let err = parse("ok\n  {{ two words }}").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 5));
+        assert_eq!(err.to_string(), "2:5: Invalid variable name `two words`");
+
+        let err = parse("a\n{% if x %}\nb").unwrap_err();
+        assert_eq!(err.to_string(), "2:1: Unclosed `if`, expected `endif`");
+        let err = parse("{% for x in xs %}{% endif %}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "1:18: Unexpected `endif`, expected `endfor`"
+        );
+        let err = parse("{% endfor %}").unwrap_err();
+        assert_eq!(err.to_string(), "1:1: Unexpected `endfor`");
+        let err = parse("{% while x %}").unwrap_err();
+        assert_eq!(err.to_string(), "1:1: Unknown tag `while x`");
+    }
+
+    #[test]
+    fn parse_legacy_tokens() {
+        assert_eq!(
+            parse_legacy("a ?body"),
+            vec![
+                Node::Text("a ".into()),
+                Node::Var {
+                    name: "body".into(),
+                    format: None,
+                    raw: "?body".into()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+use anyhow::Result;
+
+/// Action of the Advanced URI plugin
+pub const ADVANCED_ACTION: &str = "advanced-uri";
+
+/// An `obsidian://` URI. Parameters are kept in order and encoded when
+/// the URI is displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri {
+    pub action: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl Uri {
+    /// `obsidian://<action>?vault=<name>`
+    pub fn new(action: &str, vault: &Path) -> Self {
+        let name = vault.file_name().unwrap_or_default().to_string_lossy();
+        Self {
+            action: action.to_string(),
+            params: vec![],
+        }
+        .param("vault", &name)
+    }
+
+    /// Opens a vault-relative note, at a `Heading` or `^block` subpath
+    pub fn open(vault: &Path, file: &Path, subpath: Option<&str>) -> Self {
+        let mut file = uri_file(file);
+        if let Some(subpath) = subpath {
+            file = format!("{file}#{subpath}");
+        }
+        Self::new("open", vault).param("file", &file)
+    }
+
+    /// Opens the search pane with `query`
+    pub fn search(vault: &Path, query: &str) -> Self {
+        Self::new("search", vault).param("query", query)
+    }
+
+    /// Creates a note with `content` in the app. Obsidian picks another
+    /// name if the note exists, unless `overwrite` or `append` is added.
+    pub fn create(vault: &Path, file: &Path, content: &str) -> Self {
+        Self::new("new", vault)
+            .param("file", &uri_file(file))
+            .param("content", content)
+    }
+
+    /// Advanced URI plugin's action for a note, with a `heading` or `block`
+    /// parameter for the subpath. More parameters (`mode`, `data`, `line`,
+    /// `viewmode`...) are added with `param`.
+    pub fn advanced(vault: &Path, file: &Path, subpath: Option<&str>) -> Self {
+        let uri = Self::new(ADVANCED_ACTION, vault)
+            .param("filepath", &file.to_string_lossy().replace('\\', "/"));
+        match subpath {
+            Some(subpath) => match subpath.strip_prefix('^') {
+                Some(block) => uri.param("block", block),
+                None => uri.param("heading", subpath),
+            },
+            None => uri,
+        }
+    }
+
+    pub fn param(mut self, key: &str, val: &str) -> Self {
+        self.params.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Hands the URI to the OS, which opens it in Obsidian
+    pub fn launch(&self) -> Result<()> {
+        open::that(self.to_string())?;
+        Ok(())
+    }
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "obsidian://{}", self.action)?;
+        for (i, (key, val)) in self.params.iter().enumerate() {
+            let sep = if i == 0 { '?' } else { '&' };
+            write!(
+                f,
+                "{sep}{}={}",
+                urlencoding::encode(key),
+                urlencoding::encode(val)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `Note#Heading` or `Note#^block` into the note and its subpath
+pub fn split_subpath(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('#') {
+        Some((note, subpath)) if !subpath.trim().is_empty() => (note, Some(subpath.trim())),
+        Some((note, _)) => (note, None),
+        None => (target, None),
+    }
+}
+
+/// Vault-relative file with forward slashes and without `.md`, as
+/// Obsidian expects it
+fn uri_file(file: &Path) -> String {
+    let file = file.to_string_lossy().replace('\\', "/");
+    match file.strip_suffix(".md") {
+        Some(file) => file.to_string(),
+        None => file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::uri::{Uri, split_subpath};
+
+    #[test]
+    fn build_uris() {
+        let vault = Path::new("/home/me/My Vault");
+        let note = Path::new("Projects/Plan & Ideas.md");
+
+        assert_eq!(
+            Uri::open(vault, note, None).to_string(),
+            "obsidian://open?vault=My%20Vault&file=Projects%2FPlan%20%26%20Ideas"
+        );
+        assert_eq!(
+            Uri::open(vault, Path::new("Plan"), Some("^abc")).to_string(),
+            "obsidian://open?vault=My%20Vault&file=Plan%23%5Eabc"
+        );
+        assert_eq!(
+            Uri::search(vault, "tag:#work").to_string(),
+            "obsidian://search?vault=My%20Vault&query=tag%3A%23work"
+        );
+        assert_eq!(
+            Uri::create(vault, note, "a\nb")
+                .param("append", "true")
+                .to_string(),
+            "obsidian://new?vault=My%20Vault&file=Projects%2FPlan%20%26%20Ideas&content=a%0Ab&append=true"
+        );
+        assert_eq!(
+            Uri::advanced(vault, note, Some("Log"))
+                .param("mode", "append")
+                .to_string(),
+            "obsidian://advanced-uri?vault=My%20Vault&filepath=Projects%2FPlan%20%26%20Ideas.md&heading=Log&mode=append"
+        );
+        assert_eq!(
+            Uri::advanced(vault, note, Some("^abc")).params[2],
+            ("block".to_string(), "abc".to_string())
+        );
+    }
+
+    #[test]
+    fn split_subpaths() {
+        assert_eq!(split_subpath("Note#Heading"), ("Note", Some("Heading")));
+        assert_eq!(split_subpath("a/Note.md#^id"), ("a/Note.md", Some("^id")));
+        assert_eq!(split_subpath("Note#"), ("Note", None));
+        assert_eq!(split_subpath("Note"), ("Note", None));
+    }
+}
@@ -1,91 +1,475 @@
-use std::{collections::HashMap, ffi::OsStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fs::{self, Metadata},
+    io::ErrorKind,
+    panic,
+    path::{Path, PathBuf},
+    thread,
+    time::SystemTime,
+};
 
-use anyhow::Ok;
+use anyhow::{Context, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use tokio::io::AsyncReadExt;
-use tokio::task::JoinSet;
+use serde::{Deserialize, Serialize};
 use walkdir::{DirEntry, WalkDir};
 
-use crate::config::Config;
+use crate::{
+    markdown::{self, Heading, Link},
+    note::{Note, note_file},
+};
 
-static LINKS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[.*?\]\]").unwrap());
-static TAGS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"#\w+").unwrap());
+static TAGS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)#([\w/-]*[^\W\d][\w/-]*)").unwrap());
 
-type TagMap = HashMap<String, u32>;
+pub const ALIASES_KEYS: [&str; 2] = ["aliases", "alias"];
+pub const TAGS_KEYS: [&str; 2] = ["tags", "tag"];
 
-#[derive(Debug, Clone)]
-struct NoteStats {
+/// A file in the vault that is not a note
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Vault-relative path
+    pub path: PathBuf,
+    pub mtime: SystemTime,
+    pub size: u64,
+}
+
+/// What the index knows about a note without reading it again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteMeta {
+    /// Vault-relative path
+    pub path: PathBuf,
+    /// The file name without `.md`, which Obsidian uses as the title
+    pub title: String,
+    pub aliases: Vec<String>,
+    /// Frontmatter and inline tags without the `#`, sorted and unique
+    pub tags: Vec<String>,
+    /// How often each tag is used in the note
+    pub tag_uses: BTreeMap<String, u32>,
+    /// Links with ranges into the file's content
+    pub links: Vec<Link>,
+    pub headings: Vec<Heading>,
     pub word_count: usize,
-    pub link_count: usize,
-    pub tags: TagMap,
+    pub mtime: SystemTime,
+    pub size: u64,
+    /// Hash of the content, to notice a file that was touched but not changed
+    pub hash: u64,
+}
+
+impl NoteMeta {
+    /// Parses the metadata out of a note's content
+    pub fn parse(path: PathBuf, content: &str, mtime: SystemTime, size: u64) -> Self {
+        let note = Note::new(path, content.to_string());
+        let props = note.properties();
+        let list = |keys: [&str; 2]| {
+            keys.iter()
+                .filter_map(|key| props.get(key))
+                .flat_map(|val| val.to_list())
+                .collect::<Vec<_>>()
+        };
+
+        let mut tags = list(TAGS_KEYS)
+            .into_iter()
+            .flat_map(|tags| {
+                // `tags: a b` is a valid, space separated list in Obsidian
+                tags.split_whitespace()
+                    .map(|tag| tag.trim_start_matches('#').to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|tag| !tag.is_empty())
+            .collect::<Vec<_>>();
+        for (_, line) in markdown::lines(note.body()) {
+            tags.extend(
+                TAGS_REGEX
+                    .captures_iter(&markdown::mask_code_spans(line))
+                    .map(|caps| caps[1].to_string()),
+            );
+        }
+        let mut tag_uses = BTreeMap::new();
+        for tag in &tags {
+            *tag_uses.entry(tag.clone()).or_insert(0) += 1;
+        }
+        tags.sort();
+        tags.dedup();
+
+        Self {
+            title: title(&note.path),
+            aliases: list(ALIASES_KEYS),
+            tags,
+            tag_uses,
+            links: note.links(),
+            headings: note.headings(),
+            word_count: note.body().split_whitespace().count(),
+            mtime,
+            size,
+            hash: content_hash(content.as_bytes()),
+            path: note.path,
+        }
+    }
+
+    /// The same note at another path
+    pub fn moved(&self, path: PathBuf) -> Self {
+        Self {
+            title: title(&path),
+            path,
+            ..self.clone()
+        }
+    }
+
+    /// Reads a note at a path relative to `root`, or reuses `cached` when
+    /// the file's size and mtime or its content are the same. `None` when
+    /// the file is not UTF-8 text, so it can't be a note.
+    pub fn read(
+        root: &Path,
+        path: PathBuf,
+        meta: &Metadata,
+        cached: Option<&NoteMeta>,
+    ) -> Result<Option<ReadNote>> {
+        let (mtime, size) = (mtime(meta), meta.len());
+        if let Some(cached) = cached.filter(|c| c.mtime == mtime && c.size == size) {
+            return Ok(Some((cached.clone(), NoteRead::Reused)));
+        }
+        let abs_path = root.join(&path);
+        let content = match fs::read_to_string(&abs_path) {
+            Err(err) if err.kind() == ErrorKind::InvalidData => return Ok(None),
+            content => {
+                content.with_context(|| format!("Could not read note {}", abs_path.display()))?
+            }
+        };
+        if let Some(cached) = cached.filter(|c| c.hash == content_hash(content.as_bytes())) {
+            return Ok(Some((
+                Self {
+                    mtime,
+                    size,
+                    ..cached.clone()
+                },
+                NoteRead::Touched,
+            )));
+        }
+        Ok(Some((
+            Self::parse(path, &content, mtime, size),
+            NoteRead::Parsed,
+        )))
+    }
+}
+
+/// A note `NoteMeta::read` got and how
+pub type ReadNote = (NoteMeta, NoteRead);
+
+/// How `NoteMeta::read` got a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteRead {
+    /// Taken from the cache without reading the file
+    Reused,
+    /// Read, but the content is the same as in the cache
+    Touched,
+    Parsed,
+}
+
+/// What a refresh of the index did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Refresh {
+    /// Notes taken from the previous index as they were
+    pub reused: usize,
+    /// Notes with a new mtime or size but the same content, only the
+    /// index's mtime and size of them change
+    pub touched: usize,
+    /// Notes that are new or changed
+    pub parsed: usize,
+    /// Notes of the previous index that are gone
+    pub removed: usize,
+    /// `.md` files left out because they are not UTF-8 text
+    pub skipped: usize,
 }
 
+impl Refresh {
+    /// Whether the index differs from the previous one
+    pub fn changed(&self) -> bool {
+        self.touched > 0 || self.parsed > 0 || self.removed > 0
+    }
+}
+
+/// Every note, attachment and folder of a vault. Hidden entries and
+/// Obsidian's excluded folders are left out.
 #[derive(Debug, Clone, Default)]
-pub struct VaultStats {
-    pub total_word_count: usize,
-    pub total_link_count: usize,
-    pub tags: TagMap,
+pub struct Vault {
+    pub root: PathBuf,
+    /// Sorted by path
+    pub notes: Vec<NoteMeta>,
+    pub attachments: Vec<Attachment>,
+    pub folders: Vec<PathBuf>,
+    by_path: HashMap<PathBuf, usize>,
+    /// Lowercase title to notes
+    by_title: HashMap<String, Vec<usize>>,
+    /// Lowercase alias to notes
+    by_alias: HashMap<String, Vec<usize>>,
+    /// Lowercase file name to attachments
+    attachments_by_name: HashMap<String, Vec<usize>>,
 }
 
-impl VaultStats {
-    pub fn frequent_tags(&self, take: usize) -> Vec<(&String, &u32)> {
-        let mut tags_vec: Vec<_> = self.tags.iter().collect();
-        tags_vec.sort_by(|a, b| b.1.cmp(a.1));
-        tags_vec.into_iter().take(take).collect()
+impl Vault {
+    /// Walks the vault and parses every note. `excluded` are vault-relative
+    /// folders to skip.
+    pub fn load(root: &Path, excluded: &[PathBuf]) -> Result<Self> {
+        Ok(Self::refresh(root, excluded, &Self::default())?.0)
     }
 
-    pub async fn walk_vault(&mut self, cfg: &Config) -> anyhow::Result<()> {
-        let mut set: JoinSet<NoteStats> = JoinSet::new();
+    /// Walks the vault like `load`, only parsing the notes that are not in
+    /// `cached` as they are on disk
+    pub fn refresh(root: &Path, excluded: &[PathBuf], cached: &Vault) -> Result<(Self, Refresh)> {
+        let mut pending = vec![];
+        let mut attachments = vec![];
+        let mut folders = vec![];
+        let mut refresh = Refresh::default();
 
-        for entry in WalkDir::new(cfg.vault.clone())
+        for entry in WalkDir::new(root)
+            .sort_by_file_name()
             .into_iter()
-            .filter_entry(|e| !is_hidden(e))
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_file() && e.path().extension() == Some(OsStr::new("md")))
+            .filter_entry(|e| e.depth() == 0 || !is_hidden(e) && !is_excluded(root, e, excluded))
         {
-            set.spawn(async move {
-                let mut contents = String::new();
-                let mut file = tokio::fs::OpenOptions::new()
-                    .read(true)
-                    .open(entry.path())
-                    .await
-                    .unwrap();
-                file.read_to_string(&mut contents).await.unwrap();
+            let entry = entry?;
+            if entry.depth() == 0 {
+                continue;
+            }
+            let path = entry.path().strip_prefix(root)?.to_path_buf();
+            let meta = entry.metadata()?;
+            if entry.file_type().is_dir() {
+                folders.push(path);
+            } else if is_note(&path) {
+                pending.push((path, meta));
+            } else {
+                attachments.push(Attachment {
+                    path,
+                    mtime: mtime(&meta),
+                    size: meta.len(),
+                });
+            }
+        }
+        let mut notes = Vec::with_capacity(pending.len());
+        for (path, read) in read_notes(root, &pending, cached)? {
+            let Some((note, read)) = read else {
+                eprintln!("Skipping {}: not UTF-8 text", path.display());
+                refresh.skipped += 1;
+                continue;
+            };
+            match read {
+                NoteRead::Reused => refresh.reused += 1,
+                NoteRead::Touched => refresh.touched += 1,
+                NoteRead::Parsed => refresh.parsed += 1,
+            }
+            notes.push(note);
+        }
+        let vault = Self::new(root.to_path_buf(), notes, attachments, folders);
+        refresh.removed = cached
+            .notes
+            .iter()
+            .filter(|note| vault.note(&note.path).is_none())
+            .count();
+        Ok((vault, refresh))
+    }
 
-                let word_count = contents.split_ascii_whitespace().count();
-                let link_count = LINKS_REGEX.find_iter(&contents).count();
-                let mut tags: TagMap = TagMap::new();
+    /// An index of already parsed notes
+    pub fn new(
+        root: PathBuf,
+        mut notes: Vec<NoteMeta>,
+        mut attachments: Vec<Attachment>,
+        mut folders: Vec<PathBuf>,
+    ) -> Self {
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        attachments.sort_by(|a, b| a.path.cmp(&b.path));
+        folders.sort();
 
-                for t in TAGS_REGEX.find_iter(&contents) {
-                    let tag = t.as_str().to_string();
-                    *tags.entry(tag).or_insert(0) += 1;
-                }
+        let mut vault = Self {
+            root,
+            ..Default::default()
+        };
+        for (i, note) in notes.iter().enumerate() {
+            vault.by_path.insert(note.path.clone(), i);
+            vault
+                .by_title
+                .entry(note.title.to_lowercase())
+                .or_default()
+                .push(i);
+            for alias in &note.aliases {
+                vault
+                    .by_alias
+                    .entry(alias.to_lowercase())
+                    .or_default()
+                    .push(i);
+            }
+        }
+        for (i, attachment) in attachments.iter().enumerate() {
+            let name = attachment.path.file_name().unwrap_or_default();
+            vault
+                .attachments_by_name
+                .entry(name.to_string_lossy().to_lowercase())
+                .or_default()
+                .push(i);
+        }
+        vault.notes = notes;
+        vault.attachments = attachments;
+        vault.folders = folders;
+        vault
+    }
+
+    /// A note by its exact vault-relative path
+    pub fn note(&self, path: &Path) -> Option<&NoteMeta> {
+        self.by_path.get(path).map(|&i| &self.notes[i])
+    }
+
+    /// Notes with this title, ignoring case
+    pub fn by_title(&self, title: &str) -> Vec<&NoteMeta> {
+        self.lookup(&self.by_title, title)
+    }
+
+    /// Notes with this alias, ignoring case
+    pub fn by_alias(&self, alias: &str) -> Vec<&NoteMeta> {
+        self.lookup(&self.by_alias, alias)
+    }
 
-                NoteStats {
-                    link_count,
-                    word_count,
-                    tags,
-                }
-            });
+    /// An attachment by its exact vault-relative path
+    pub fn attachment(&self, path: &Path) -> Option<&Attachment> {
+        self.attachments
+            .binary_search_by(|attachment| attachment.path.as_path().cmp(path))
+            .ok()
+            .map(|i| &self.attachments[i])
+    }
+
+    /// Attachments with this file name, ignoring case
+    pub fn attachments_by_name(&self, name: &str) -> Vec<&Attachment> {
+        self.attachments_by_name
+            .get(&name.to_lowercase())
+            .map(|ids| ids.iter().map(|&i| &self.attachments[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Notes a query could mean: the note at its path (`.md` optional), or
+    /// else the notes with it as title, or else as alias
+    pub fn matches(&self, query: &str) -> Vec<&NoteMeta> {
+        if let Some(note) = self.note(&note_file(Path::new(query))) {
+            return vec![note];
         }
+        let found = self.by_title(query.strip_suffix(".md").unwrap_or(query));
+        match found.is_empty() {
+            true => self.by_alias(query),
+            false => found,
+        }
+    }
 
-        while let Some(res) = set.join_next().await {
-            self.merge(res?);
+    /// The only note a query could mean
+    pub fn find(&self, query: &str) -> Result<&NoteMeta> {
+        match self.matches(query).as_slice() {
+            [note] => Ok(note),
+            [] => Err(anyhow::Error::msg(format!("No note named `{query}`"))),
+            notes => Err(anyhow::Error::msg(format!(
+                "`{query}` matches several notes: {}",
+                notes
+                    .iter()
+                    .map(|note| note.path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
         }
+    }
 
-        Ok(())
+    fn lookup(&self, map: &HashMap<String, Vec<usize>>, key: &str) -> Vec<&NoteMeta> {
+        map.get(&key.to_lowercase())
+            .map(|ids| ids.iter().map(|&i| &self.notes[i]).collect())
+            .unwrap_or_default()
     }
+}
+
+type TagMap = BTreeMap<String, u32>;
+
+#[derive(Debug, Clone, Default)]
+pub struct VaultStats {
+    /// Words of the note bodies, without frontmatter
+    pub total_word_count: usize,
+    /// Links to notes and attachments, web links left out
+    pub total_link_count: usize,
+    /// `#tag` to the number of times it is used, in frontmatter or inline
+    pub tags: TagMap,
+}
 
-    fn merge(&mut self, note: NoteStats) {
-        self.total_link_count += note.link_count;
-        self.total_word_count += note.word_count;
-        for (tag, count) in note.tags {
-            *self.tags.entry(tag).or_insert(0) += count;
+impl VaultStats {
+    pub fn new(vault: &Vault) -> Self {
+        let mut stats = Self::default();
+        for note in &vault.notes {
+            stats.total_link_count += note.links.len();
+            stats.total_word_count += note.word_count;
+            for (tag, uses) in &note.tag_uses {
+                *stats.tags.entry(format!("#{tag}")).or_insert(0) += uses;
+            }
         }
+        stats
+    }
+
+    pub fn frequent_tags(&self, take: usize) -> Vec<(&String, &u32)> {
+        let mut tags_vec: Vec<_> = self.tags.iter().collect();
+        tags_vec.sort_by(|a, b| b.1.cmp(a.1));
+        tags_vec.into_iter().take(take).collect()
     }
 }
 
+pub fn is_note(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("md"))
+}
+
+/// 64-bit FNV-1a, which unlike std's hasher is the same on every build
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Reads notes on every core, as parsing is most of the time of loading
+/// a large vault
+fn read_notes<'a>(
+    root: &Path,
+    pending: &'a [(PathBuf, Metadata)],
+    cached: &Vault,
+) -> Result<Vec<(&'a Path, Option<ReadNote>)>> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_len = pending.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let workers = pending
+            .chunks(chunk_len)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(path, meta)| {
+                            let read = NoteMeta::read(root, path.clone(), meta, cached.note(path))?;
+                            Ok((path.as_path(), read))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut read = Vec::with_capacity(pending.len());
+        for worker in workers {
+            read.extend(
+                worker
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))?,
+            );
+        }
+        Ok(read)
+    })
+}
+
+fn title(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn mtime(meta: &Metadata) -> SystemTime {
+    meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
 fn is_hidden(entry: &DirEntry) -> bool {
     entry
         .file_name()
@@ -94,9 +478,16 @@ fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
+fn is_excluded(root: &Path, entry: &DirEntry, excluded: &[PathBuf]) -> bool {
+    let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+    excluded.iter().any(|folder| path.starts_with(folder))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::vault::VaultStats;
+    use std::{env, fs, path::PathBuf, time::SystemTime};
+
+    use crate::vault::{NoteMeta, Vault, VaultStats};
 
     #[test]
     fn frequent_tags() {
@@ -116,4 +507,124 @@ mod tests {
         assert_eq!(freq_tags[1], (&("#second".to_string()), &3));
         assert_eq!(freq_tags[2], (&("#third".to_string()), &2));
     }
+
+    #[test]
+    fn stats_of_notes() {
+        let notes = [
+            "---\ntags: [work]\ntitle: Not counted words\n---\nOne #work [[Plan]] [Goals](Plan.md#Goals) [site](https://example.com)\n",
+            "#2025 is a year, #work again\n```\n[[Not a link]] #code\n```\n",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, content)| {
+            NoteMeta::parse(
+                PathBuf::from(format!("{i}.md")),
+                content,
+                SystemTime::UNIX_EPOCH,
+                content.len() as u64,
+            )
+        })
+        .collect();
+        let vault = Vault::new(PathBuf::new(), notes, vec![], vec![]);
+        let stats = VaultStats::new(&vault);
+
+        // Words of the bodies, not the frontmatter. Code blocks count too.
+        assert_eq!(stats.total_word_count, 17);
+        // Links to notes the parser finds, not web links or those in code
+        assert_eq!(stats.total_link_count, 2);
+        // The frontmatter tag and two inline uses, numbers are no tags
+        assert_eq!(stats.tags["#work"], 3);
+        assert_eq!(stats.tags.len(), 1);
+    }
+
+    #[test]
+    fn parse_note_meta() {
+        let content = "---\naliases: [Plan]\ntags: [work, \"#q3\"]\n---\n# Roadmap\nSee [[Other]] #idea and #2025 `#code` #work\n```\n#not-a-tag\n```\n";
+        let note = NoteMeta::parse(
+            PathBuf::from("Projects/Roadmap.md"),
+            content,
+            SystemTime::UNIX_EPOCH,
+            content.len() as u64,
+        );
+        assert_eq!(note.title, "Roadmap");
+        assert_eq!(note.aliases, vec!["Plan"]);
+        assert_eq!(note.tags, vec!["idea", "q3", "work"]);
+        assert_eq!(note.tag_uses["work"], 2);
+        assert_eq!(note.links[0].target, "Other");
+        assert_eq!(&content[note.links[0].range.clone()], "[[Other]]");
+        assert_eq!(note.headings[0].text, "Roadmap");
+        assert_eq!(note.word_count, 12);
+    }
+
+    #[test]
+    fn load_and_find() {
+        let root = env::temp_dir().join("obsidian-rust-cli-vault");
+        let _ = fs::remove_dir_all(&root);
+        for dir in [".obsidian", "a", "b", "Archive/old", "assets"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join("a/Note.md"),
+            "---\nalias: Shared\n---\n#one [[b/Note]] #one",
+        )
+        .unwrap();
+        fs::write(root.join("b/Note.md"), "two words").unwrap();
+        fs::write(root.join("b/Unique.md"), "---\naliases: [Shared]\n---\n").unwrap();
+        fs::write(root.join("Archive/old/Gone.md"), "").unwrap();
+        fs::write(root.join("b/Meeting 10.15.md"), "").unwrap();
+        // Not UTF-8, so not a note, but the rest of the vault still loads
+        fs::write(root.join("b/Binary.md"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(root.join(".obsidian/app.json"), "{}").unwrap();
+        fs::write(root.join("assets/Image.PNG"), [0u8; 4]).unwrap();
+
+        let (vault, refresh) =
+            Vault::refresh(&root, &[PathBuf::from("Archive")], &Vault::default()).unwrap();
+        assert_eq!((refresh.parsed, refresh.skipped), (4, 1));
+        let paths = vault
+            .notes
+            .iter()
+            .map(|note| note.path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "a/Note.md",
+                "b/Meeting 10.15.md",
+                "b/Note.md",
+                "b/Unique.md"
+            ]
+        );
+        assert_eq!(vault.attachments[0].size, 4);
+        assert_eq!(vault.attachments_by_name("image.png").len(), 1);
+        assert_eq!(
+            vault.folders,
+            vec![
+                PathBuf::from("a"),
+                PathBuf::from("assets"),
+                PathBuf::from("b")
+            ]
+        );
+
+        assert_eq!(
+            vault.find("a/Note").unwrap().path,
+            PathBuf::from("a/Note.md")
+        );
+        assert_eq!(
+            vault.find("unique").unwrap().path,
+            PathBuf::from("b/Unique.md")
+        );
+        assert_eq!(
+            vault.find("b/Meeting 10.15").unwrap().path,
+            PathBuf::from("b/Meeting 10.15.md")
+        );
+        assert!(vault.find("Note").is_err());
+        assert!(vault.find("shared").is_err());
+        assert!(vault.find("Gone").is_err());
+
+        let stats = VaultStats::new(&vault);
+        assert_eq!(stats.total_link_count, 1);
+        assert_eq!(stats.total_word_count, 5);
+        assert_eq!(stats.tags["#one"], 2);
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
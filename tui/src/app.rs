@@ -22,7 +22,7 @@ impl App {
     pub(crate) fn new(cfg: Config) -> Self {
         Self {
             screen_select: Screen::New,
-            new_note_screen: NewScreen::new(),
+            new_note_screen: NewScreen::new(&cfg),
             show_note_screen: ShowScreen::new(),
             cfg,
         }
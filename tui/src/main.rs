@@ -20,11 +20,20 @@ struct Args {
     /// Path to template
     #[arg(short, long)]
     pub template: Option<PathBuf>,
+
+    /// Path to a config file layered over the user config
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Named vault profile from the config file
+    #[arg(short, long)]
+    pub profile: Option<String>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let cfg = Config::build(args.vault, args.template).unwrap_or_else(|_| exit(1));
+    let cfg = Config::build(args.vault, args.template, args.config, args.profile)
+        .unwrap_or_else(|_| exit(1));
 
     color_eyre::install()?;
     let terminal = ratatui::init();
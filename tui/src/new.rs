@@ -1,6 +1,6 @@
 use anyhow::Error;
 use chrono::Local;
-use cli_core::{config::Config, note::Note, template::TemplArgs};
+use cli_core::{config::Config, naming::NameVars, note::Note, template::Template};
 use ratatui::{
     Frame,
     crossterm::event::{KeyCode, KeyEvent, KeyEventKind},
@@ -9,7 +9,6 @@ use ratatui::{
     text::{Line, Text},
     widgets::{Block, Paragraph},
 };
-use std::{fs::File, path::PathBuf};
 
 use crate::input::{Input, InputMode};
 
@@ -22,17 +21,59 @@ struct CreatedNote {
 pub(crate) struct NewScreen {
     input: Input,
     input_mode: InputMode,
+    /// The configured template followed by the templates folder
+    templates: Vec<(String, Template)>,
+    selected: usize,
+    /// The template's prompts, filled in before the idea
+    prompts: Vec<(String, Input)>,
+    /// Index of the edited prompt, `prompts.len()` being the idea
+    focus: usize,
     created_note: Option<CreatedNote>,
     error_msg: Option<String>,
 }
 
 impl NewScreen {
-    pub(crate) const fn new() -> Self {
-        Self {
+    pub(crate) fn new(cfg: &Config) -> Self {
+        let mut screen = Self {
             input: Input::new(),
             input_mode: InputMode::Normal,
+            templates: vec![("default".to_string(), cfg.template.clone())],
+            selected: 0,
+            prompts: vec![],
+            focus: 0,
             created_note: None,
             error_msg: None,
+        };
+        if cfg.templates_folder.is_some() {
+            match cfg.library().and_then(|library| library.list()) {
+                Ok(templates) => screen.templates.extend(templates),
+                Err(err) => screen.error_msg = Some(err.to_string()),
+            }
+        }
+        screen.select_template(0);
+        screen
+    }
+
+    /// Picks a template and replaces the prompt fields with its prompts
+    fn select_template(&mut self, index: usize) {
+        self.selected = index % self.templates.len();
+        self.focus = 0;
+        self.prompts = match self.templates[self.selected].1.prompts() {
+            Ok(prompts) => prompts
+                .into_iter()
+                .map(|prompt| (prompt, Input::new()))
+                .collect(),
+            Err(err) => {
+                self.error_msg = Some(err.to_string());
+                vec![]
+            }
+        };
+    }
+
+    fn focused(&mut self) -> &mut Input {
+        match self.prompts.get_mut(self.focus) {
+            Some((_, input)) => input,
+            None => &mut self.input,
         }
     }
 
@@ -42,17 +83,26 @@ impl NewScreen {
                 KeyCode::Char('e') => {
                     self.input_mode = InputMode::Editing;
                 }
+                KeyCode::Char('t') if relay.kind == KeyEventKind::Press => {
+                    self.select_template(self.selected + 1)
+                }
                 KeyCode::Char('q') => {}
                 _ => {}
             },
             InputMode::Editing if relay.kind == KeyEventKind::Press => match relay.code {
+                KeyCode::Enter | KeyCode::Tab | KeyCode::Down
+                    if self.focus < self.prompts.len() =>
+                {
+                    self.focus += 1
+                }
+                KeyCode::BackTab | KeyCode::Up => self.focus = self.focus.saturating_sub(1),
                 KeyCode::Enter if !self.input.input.trim_ascii().is_empty() => {
                     self.submit_idea(cfg)
                 }
-                KeyCode::Char(to_insert) => self.input.enter_char(to_insert),
-                KeyCode::Backspace => self.input.delete_char(),
-                KeyCode::Left => self.input.move_cursor_left(),
-                KeyCode::Right => self.input.move_cursor_right(),
+                KeyCode::Char(to_insert) => self.focused().enter_char(to_insert),
+                KeyCode::Backspace => self.focused().delete_char(),
+                KeyCode::Left => self.focused().move_cursor_left(),
+                KeyCode::Right => self.focused().move_cursor_right(),
                 KeyCode::Esc => self.input_mode = InputMode::Normal,
                 _ => {}
             },
@@ -65,40 +115,63 @@ impl NewScreen {
             self.error_msg = Some(err.to_string());
             None
         });
+        for (_, input) in &mut self.prompts {
+            input.input.clear();
+            input.reset_cursor();
+        }
         self.input.input.clear();
         self.input.reset_cursor();
+        self.focus = 0;
     }
 
     pub(crate) fn draw(&mut self, frame: &mut Frame) {
-        let vertical = Layout::vertical([
-            Constraint::Length(1),
-            Constraint::Min(1),
-            Constraint::Length(3),
-        ]);
-        let [help_area, input_area, info_area] = vertical.areas(frame.area());
-
-        self.render_help(frame, help_area);
-        self.render_input(frame, input_area);
-        self.render_info_error(frame, info_area);
+        let mut constraints = vec![Constraint::Length(1)];
+        constraints.extend(self.prompts.iter().map(|_| Constraint::Length(3)));
+        constraints.extend([Constraint::Min(1), Constraint::Length(3)]);
+        let areas = Layout::vertical(constraints).split(frame.area());
+
+        self.render_help(frame, areas[0]);
+        for (i, (prompt, input)) in self.prompts.iter().enumerate() {
+            self.render_input(frame, areas[i + 1], prompt, input, i == self.focus);
+        }
+        let idea_area = areas[self.prompts.len() + 1];
+        let focused = self.focus == self.prompts.len();
+        let title = format!("Idea ({})", self.templates[self.selected].0);
+        self.render_input(frame, idea_area, &title, &self.input, focused);
+        self.render_info_error(frame, areas[self.prompts.len() + 2]);
     }
 
     fn create_new_note(&mut self, cfg: &Config) -> Result<Option<CreatedNote>, Error> {
-        let mut note_path: PathBuf = cfg.vault.clone();
-        let formatted = format!("{}", Local::now().format("%Y_%m_%d_%H_%M_%S"));
-        let title = format!("Note_{formatted}.md");
-        note_path.push(title.clone());
-
-        let handle = File::create(note_path.as_path())?;
-
-        let body = cfg.template.render(&TemplArgs {
-            body: self.input.input.clone(),
-            date: formatted.clone(),
-        })?;
-
-        let mut note = Note::new(&handle, &note_path, Some(body.clone()));
-        note.write_file_handle()?;
-
-        Ok(Some(CreatedNote { body, title }))
+        let templ = &self.templates[self.selected].1;
+        let now = Local::now();
+        let name_vars = NameVars {
+            title: None,
+            idea: &self.input.input,
+        };
+        let note_path = cfg.new_note_path(templ, &now, None, &name_vars)?;
+        let title = note_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let mut context = cfg.template_context(now.naive_local(), title.trim_end_matches(".md"));
+        for (prompt, input) in &self.prompts {
+            context.insert(prompt.as_str(), input.input.clone());
+        }
+        context.insert("body", self.input.input.clone());
+        let mut note = Note::new(note_path, templ.render(&context)?).with_fsync(cfg.fsync);
+        note.create()?;
+
+        Ok(Some(CreatedNote {
+            title: note
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            body: note.content,
+        }))
     }
 
     fn render_help(&self, frame: &mut Frame, area: Rect) {
@@ -109,7 +182,9 @@ impl NewScreen {
                     "Del".bold(),
                     " to exit, ".not_bold(),
                     "e".bold(),
-                    " to start typing an idea. ".bold(),
+                    " to start typing an idea, ".bold(),
+                    "t".bold(),
+                    " to pick a template. ".not_bold(),
                     "Press ".not_bold(),
                     "Insert".bold(),
                     " to switch to displaying notes.".not_bold(),
@@ -121,6 +196,8 @@ impl NewScreen {
                     "Press ".not_bold(),
                     "Esc".bold(),
                     " to stop typing, ".not_bold(),
+                    "Tab".bold(),
+                    " to switch fields, ".not_bold(),
                     "Enter".bold(),
                     " to create a note.".not_bold(),
                 ],
@@ -133,23 +210,31 @@ impl NewScreen {
         frame.render_widget(help_message, area);
     }
 
-    fn render_input(&self, frame: &mut Frame, area: Rect) {
-        let input = Paragraph::new(self.input.input.as_str())
-            .style(match self.input_mode {
-                InputMode::Normal => Style::default(),
-                InputMode::Editing => Style::default().fg(Color::Rgb(126u8, 29u8, 251u8)),
+    fn render_input(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        input: &Input,
+        focused: bool,
+    ) {
+        let editing = focused && matches!(self.input_mode, InputMode::Editing);
+        let widget = Paragraph::new(input.input.as_str())
+            .style(if editing {
+                Style::default().fg(Color::Rgb(126u8, 29u8, 251u8))
+            } else {
+                Style::default()
             })
-            .block(Block::bordered().title("Idea"));
+            .block(Block::bordered().title(title));
 
-        frame.render_widget(input, area);
+        frame.render_widget(widget, area);
 
-        match self.input_mode {
-            InputMode::Normal => {}
-            #[allow(clippy::cast_possible_truncation)]
-            InputMode::Editing => frame.set_cursor_position(Position::new(
-                area.x + self.input.character_index as u16 + 1,
+        #[allow(clippy::cast_possible_truncation)]
+        if editing {
+            frame.set_cursor_position(Position::new(
+                area.x + input.character_index as u16 + 1,
                 area.y + 1,
-            )),
+            ));
         }
     }
 
@@ -1,13 +1,19 @@
-use cli_core::config::Config;
+use chrono::Local;
+use cli_core::{
+    config::Config,
+    dates,
+    links::Backlink,
+    note::Note,
+    periodic::{Period, PeriodicNote},
+};
 use ratatui::{
     Frame,
     crossterm::event::{KeyCode, KeyEvent, KeyEventKind},
     layout::{Constraint, Layout, Position, Rect},
     style::{Color, Style, Stylize},
     text::{Line, Text},
-    widgets::{Block, Paragraph},
+    widgets::{Block, Paragraph, Wrap},
 };
-use std::{fs::File, io::Read, path::PathBuf};
 
 use crate::input::Input;
 
@@ -15,6 +21,7 @@ pub(crate) struct ShowScreen {
     input: Input,
     vertical_scroll: usize,
     note_content: Option<String>,
+    backlinks: Vec<Backlink>,
     error_msg: Option<String>,
 }
 
@@ -24,6 +31,7 @@ impl ShowScreen {
             vertical_scroll: 0,
             error_msg: None,
             note_content: None,
+            backlinks: Vec::new(),
             input: Input::new(),
         }
     }
@@ -52,13 +60,20 @@ impl ShowScreen {
     }
 
     pub(crate) fn search(&mut self, cfg: &Config) -> anyhow::Result<()> {
-        let abs_path = cfg.get_full_path(&PathBuf::from(&self.input.input))?;
-        let mut handle = File::open(abs_path.as_path())?;
-
-        let mut buf = String::new();
-        handle.read_to_string(&mut buf)?;
+        // `@yesterday`, `@-2d`... show a daily note
+        let path = match self.input.input.trim().strip_prefix('@') {
+            Some(date) => {
+                let date = dates::parse(date, Local::now().date_naive())?;
+                PeriodicNote::resolve(cfg, Period::Daily)?.path(date)
+            }
+            None => cfg.find_note(self.input.input.trim())?,
+        };
+        let note = Note::load(&cfg.vault, &path)?;
+        let path = note.path.strip_prefix(&cfg.vault).unwrap_or(&note.path);
+        self.backlinks = cfg.index()?.backlinks(path)?;
 
-        self.note_content = Some(buf);
+        self.note_content = Some(note.content);
+        self.vertical_scroll = 0;
         self.input.input.clear();
         self.input.reset_cursor();
         self.error_msg = None;
@@ -77,7 +92,14 @@ impl ShowScreen {
 
         self.render_help(frame, help_area);
         self.render_input(frame, input_area);
-        self.render_note(frame, note_area);
+        if self.note_content.is_some() {
+            let horizontal = Layout::horizontal([Constraint::Min(1), Constraint::Percentage(35)]);
+            let [note_area, backlinks_area] = horizontal.areas(note_area);
+            self.render_note(frame, note_area);
+            self.render_backlinks(frame, backlinks_area);
+        } else {
+            self.render_note(frame, note_area);
+        }
         self.render_error(frame, info_area);
     }
 
@@ -93,7 +115,9 @@ impl ShowScreen {
     fn render_help(&mut self, frame: &mut Frame, area: Rect) {
         let (msg, style) = (
             vec![
-                "Type out the path of the note to display. ".not_bold(),
+                "Type out the path, title or alias of the note to display, or ".not_bold(),
+                "@date ".bold(),
+                "for a daily note. ".not_bold(),
                 "Use ".not_bold(),
                 "Arrows ".bold(),
                 "to scroll the note.".not_bold(),
@@ -126,4 +150,22 @@ impl ShowScreen {
         .scroll((self.vertical_scroll as u16, 0));
         frame.render_widget(note, area);
     }
+
+    fn render_backlinks(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self
+            .backlinks
+            .iter()
+            .flat_map(|backlink| {
+                [
+                    Line::from(format!("{}:{}", backlink.from.display(), backlink.line)).bold(),
+                    Line::from(backlink.context.as_str()),
+                    Line::from(""),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let backlinks = Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(format!("Backlinks ({})", self.backlinks.len())));
+        frame.render_widget(backlinks, area);
+    }
 }